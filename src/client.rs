@@ -0,0 +1,481 @@
+use pinocchio::program_error::ProgramError;
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    message::{
+        v0, AccountMeta, AddressLookupTableAccount, CompileError, Instruction, VersionedMessage,
+    },
+    pubkey::Pubkey,
+    rent::Rent,
+};
+
+use solana_winternitz::{pubkey::WinternitzPubkey, signature::WinternitzSignature};
+
+use crate::{
+    close::CloseVault,
+    error::VaultError,
+    split::SplitVault,
+    state::VaultState,
+    vault::{Salt, SIGNATURE_LEN},
+};
+
+// Decode a vault account's raw data (e.g. from `getAccountInfo`) into its structured state
+pub fn parse_vault_account(data: &[u8]) -> Result<VaultState, VaultError> {
+    VaultState::try_from(data)
+}
+
+// Decode a batch of accounts in request order (e.g. from `getMultipleAccounts`). Missing accounts
+// and accounts that aren't readable vaults come back as None.
+pub fn parse_vault_accounts(accounts: &[Option<&[u8]>]) -> Vec<Option<VaultState>> {
+    accounts
+        .iter()
+        .map(|data| data.and_then(|data| parse_vault_account(data).ok()))
+        .collect()
+}
+
+// Vault address and canonical bump for a merklized winternitz pubkey hash
+pub fn vault_address(pubkey_hash: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[pubkey_hash], &Pubkey::new_from_array(crate::ID))
+}
+
+// Same check a spend makes on-chain: does this key, salt and bump derive the vault? Run it before
+// funding the vault. Pass `Salt::default()` for a vault without a passphrase.
+pub fn verify_vault_matches(
+    vault: &Pubkey,
+    winternitz_pubkey: &WinternitzPubkey,
+    bump: u8,
+    salt: &Salt,
+) -> bool {
+    solana_nostd_sha256::hashv(&[
+        salt.seed(&winternitz_pubkey.merklize()).as_ref(),
+        &[bump],
+        crate::ID.as_ref(),
+        b"ProgramDerivedAddress",
+    ])
+    .eq(vault.as_ref())
+}
+
+// Bump to pass to open and every spend. For an open vault it's also stored in the vault account
+// (`parse_vault_account(data)?.bump`), which saves the search.
+pub fn canonical_bump(pubkey_hash: &[u8; 32]) -> u8 {
+    vault_address(pubkey_hash).1
+}
+
+// Human-readable message for a transaction's custom error code, for wallet UIs and logs
+pub fn explain(code: u32) -> &'static str {
+    match code {
+        c if c == VaultError::InvalidVaultState as u32 => {
+            "vault account data has an unexpected length"
+        }
+        c if c == VaultError::InvalidDiscriminator as u32 => "account is not a vault",
+        c if c == VaultError::KeyNotBurned as u32 => {
+            "vault key hasn't been burned, so its rent can't be reclaimed"
+        }
+        c if c == VaultError::VaultNotEmpty as u32 => "vault still holds more than its rent",
+        c if c == VaultError::InvalidRentCollector as u32 => {
+            "rent can only go to the vault's opener or the configured rent collector"
+        }
+        c if c == VaultError::AccountAliasing as u32 => {
+            "the same account was passed for two roles that must be distinct"
+        }
+        c if c == VaultError::InvalidCommitment as u32 => {
+            "the vault's canonical commitment wasn't made by this account"
+        }
+        c if c == VaultError::UnsupportedAbiVersion as u32 => {
+            "instruction uses an ABI version this program doesn't support"
+        }
+        c if c == VaultError::VaultAlreadyExists as u32 => "a vault already exists at this address",
+        c if c == VaultError::MessageTooLong as u32 => "spend message has too many recipients",
+        c if c == VaultError::InsufficientFunds as u32 => "amount exceeds the vault's balance",
+        c if c == VaultError::VaultAlreadyClosed as u32 => {
+            "vault is already closed or was never opened"
+        }
+        c if c == VaultError::UnsupportedStateVersion as u32 => {
+            "vault account uses a newer layout; upgrade your client"
+        }
+        c if c == VaultError::NonCanonicalBump as u32 => {
+            "vault bump isn't the canonical one; use client::canonical_bump"
+        }
+        c if c == VaultError::InvalidAuthorization as u32 => {
+            "authorization isn't made out to this recipient, refund or payer"
+        }
+        c if c == VaultError::InsufficientComputeBudget as u32 => {
+            "not enough compute units to verify the signature; raise the compute unit limit"
+        }
+        c if c == VaultError::RecipientNotRentExempt as u32 => {
+            "a new recipient account must receive at least its rent-exempt minimum"
+        }
+        c if c == VaultError::CommitmentNotExpired as u32 => {
+            "the commitment can't be cancelled before it expires"
+        }
+        _ => "unknown error",
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spendability {
+    Spendable, // open, unburned and holding more than its rent
+    NotOpen,   // account doesn't exist, was closed, or isn't a vault
+    Unfunded,  // open, but holds nothing beyond its rent-exempt minimum
+    Burned,    // the vault's key was already used (e.g. to prove ownership)
+}
+
+/*
+    Winternitz keys are single-use, so check the vault before signing anything with its key: a
+    spend that fails on-chain still reveals part of the key. `lamports` and `data` come from the
+    vault account (`getAccountInfo`), `rent_exempt_minimum` from `getMinimumBalanceForRentExemption`
+    for `VaultState::LEN`. There's no lock on vaults, so an open, unburned, funded vault is
+    spendable.
+*/
+pub fn vault_spendability(lamports: u64, data: &[u8], rent_exempt_minimum: u64) -> Spendability {
+    let Ok(state) = parse_vault_account(data) else {
+        return Spendability::NotOpen;
+    };
+
+    if lamports == 0 {
+        Spendability::NotOpen
+    } else if state.burned {
+        Spendability::Burned
+    } else if lamports <= rent_exempt_minimum {
+        Spendability::Unfunded
+    } else {
+        Spendability::Spendable
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultLifecycle {
+    Uninitialized, // no vault at this address: never opened, or closed and removed by the runtime
+    OpenUnfunded,  // open, holding nothing beyond its rent-exempt minimum
+    OpenFunded,    // open and holding funds
+    Burned,        // still open, but its key signed something without closing it (e.g. a proof)
+}
+
+/*
+    Lifecycle of a vault address for explorers and wallet UIs, from the account as returned by
+    `getAccountInfo` (None if it doesn't exist) and `getMinimumBalanceForRentExemption` for
+    `VaultState::LEN`. A vault emptied by a split or close is removed by the runtime, so it reads
+    as Uninitialized, same as an address that was never opened: tell them apart from the
+    address's transaction history if needed. System-owned lamports sent to an unopened address
+    don't make it a vault either.
+*/
+pub fn vault_lifecycle(account: Option<&Account>, rent_exempt_minimum: u64) -> VaultLifecycle {
+    let Some(account) = account.filter(|account| account.lamports > 0) else {
+        return VaultLifecycle::Uninitialized;
+    };
+    if account.owner != Pubkey::new_from_array(crate::ID) {
+        return VaultLifecycle::Uninitialized;
+    }
+    let Ok(state) = parse_vault_account(&account.data) else {
+        return VaultLifecycle::Uninitialized;
+    };
+
+    if state.burned {
+        VaultLifecycle::Burned
+    } else if account.lamports <= rent_exempt_minimum {
+        VaultLifecycle::OpenUnfunded
+    } else {
+        VaultLifecycle::OpenFunded
+    }
+}
+
+/*
+    Lamports a user can split out of a vault while keeping its rent reserve back, i.e. its balance
+    above the rent-exempt minimum for `state_size` (VaultState::LEN). This is the whole spendable
+    amount under the `rent-collector` feature; by default a split may also spend the reserve (see
+    the README), so this is the conservative figure to show users.
+*/
+pub fn spendable_balance(account_lamports: u64, state_size: usize, rent: &Rent) -> u64 {
+    account_lamports.saturating_sub(rent.minimum_balance(state_size))
+}
+
+// Balances a split leaves behind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitOutcome {
+    pub split: u64,     // split account's balance
+    pub refund: u64,    // refund account's balance
+    pub vault: u64,     // always 0, a split closes the vault
+    pub collector: u64, // rent paid to the rent collector (0 without the `rent-collector` feature)
+}
+
+/*
+    Predict the balances after a split so UIs can preview it before the key is used, with the
+    program's arithmetic: the split account gets `amount`, the vault's rent goes to the collector
+    under `rent-collector`, and the refund account gets whatever is left as the vault is closed.
+    `split_lamports` and `refund_lamports` are the accounts' current balances, 0 if they don't
+    exist yet, and the two must be different accounts. Fails with the error the split would,
+    including ArithmeticOverflow when a balance would overflow.
+*/
+pub fn preview_split(
+    vault_lamports: u64,
+    amount: u64,
+    split_lamports: u64,
+    refund_lamports: u64,
+    rent: &Rent,
+) -> Result<SplitOutcome, ProgramError> {
+    let collector = if cfg!(feature = "rent-collector") {
+        rent.minimum_balance(VaultState::LEN).min(vault_lamports)
+    } else {
+        0
+    };
+
+    let remainder = (vault_lamports - collector)
+        .checked_sub(amount)
+        .ok_or(VaultError::InsufficientFunds)?;
+
+    // New accounts must end up rent-exempt, and a new refund account may be a vault's address
+    if split_lamports == 0 && amount > 0 && amount < rent.minimum_balance(0) {
+        return Err(VaultError::RecipientNotRentExempt.into());
+    }
+    if refund_lamports == 0 && remainder > 0 && remainder < rent.minimum_balance(VaultState::LEN) {
+        return Err(VaultError::RecipientNotRentExempt.into());
+    }
+
+    // The program credits both accounts with checked arithmetic
+    Ok(SplitOutcome {
+        split: split_lamports
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+        refund: refund_lamports
+            .checked_add(remainder)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+        vault: 0,
+        collector,
+    })
+}
+
+// Maximum size of a serialized transaction (the packet data size)
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+// Bytes taken by a length prefix encoded as a compact-u16 (shortvec)
+fn compact_u16_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+/*
+    Size of an instruction once compiled into a transaction message: program id index, shortvec of
+    account indices (one byte each) and shortvec of instruction data (discriminator included).
+    The account keys themselves live in the message header and are shared between instructions.
+*/
+pub fn compiled_instruction_size(num_accounts: usize, data_len: usize) -> usize {
+    1 + compact_u16_len(num_accounts) + num_accounts + compact_u16_len(data_len) + data_len
+}
+
+pub fn split_instruction_size() -> usize {
+    compiled_instruction_size(3, 1 + crate::split::SplitVaultInstructionData::LEN)
+}
+
+pub fn close_instruction_size() -> usize {
+    compiled_instruction_size(2, 1 + crate::close::CloseVaultInstructionData::LEN)
+}
+
+/*
+    Compile a v0 message that loads the split/close accounts (vault, split, refund) from an address
+    lookup table, saving 31 bytes per account compared to a legacy message. That room can go to
+    other instructions, such as compute budget ones, next to the 896-byte signature. The payer and
+    program ids are never looked up, as signers and invoked programs must be static keys.
+*/
+pub fn v0_message_with_lookup_table(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_table: &AddressLookupTableAccount,
+    recent_blockhash: Hash,
+) -> Result<VersionedMessage, CompileError> {
+    Ok(VersionedMessage::V0(v0::Message::try_compile(
+        payer,
+        instructions,
+        core::slice::from_ref(lookup_table),
+        recent_blockhash,
+    )?))
+}
+
+// Instruction data for a split: discriminator, signature, bump, amount, then the salt when built
+// with `passphrase-salt` (the salt is ignored otherwise, pass `Salt::default()` for none)
+pub fn split_instruction_data(
+    signature: WinternitzSignature,
+    bump: u8,
+    amount: u64,
+    salt: &Salt,
+) -> Vec<u8> {
+    let signature: [u8; SIGNATURE_LEN] = signature.into();
+
+    let mut data = Vec::with_capacity(1 + crate::split::SplitVaultInstructionData::LEN);
+    data.push(*SplitVault::DISCRIMINATOR);
+    data.extend_from_slice(&signature);
+    data.push(bump);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&salt.0[..Salt::LEN]);
+    data
+}
+
+// Instruction data for a close: discriminator, signature, bump, then the salt when built with
+// `passphrase-salt` (the salt is ignored otherwise, pass `Salt::default()` for none)
+pub fn close_instruction_data(signature: WinternitzSignature, bump: u8, salt: &Salt) -> Vec<u8> {
+    let signature: [u8; SIGNATURE_LEN] = signature.into();
+
+    let mut data = Vec::with_capacity(1 + crate::close::CloseVaultInstructionData::LEN);
+    data.push(*CloseVault::DISCRIMINATOR);
+    data.extend_from_slice(&signature);
+    data.push(bump);
+    data.extend_from_slice(&salt.0[..Salt::LEN]);
+    data
+}
+
+// The keys an instruction is meant to use, by role
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultAccounts {
+    Open {
+        payer: Pubkey,
+        vault: Pubkey,
+    },
+    Split {
+        vault: Pubkey,
+        split: Pubkey,
+        refund: Pubkey,
+    },
+    Close {
+        vault: Pubkey,
+        refund: Pubkey,
+    },
+}
+
+impl VaultAccounts {
+    // The accounts in the order and with the flags the program reads them
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let metas = match *self {
+            Self::Open { payer, vault } => vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(Pubkey::default(), false), // system program
+            ],
+            Self::Split {
+                vault,
+                split,
+                refund,
+            } => vec![
+                AccountMeta::new(vault, false),
+                AccountMeta::new(split, false),
+                AccountMeta::new(refund, false),
+            ],
+            Self::Close { vault, refund } => vec![
+                AccountMeta::new(vault, false),
+                AccountMeta::new(refund, false),
+            ],
+        };
+
+        // Split and close take the rent collector last
+        #[cfg(feature = "rent-collector")]
+        let metas = match self {
+            Self::Open { .. } => metas,
+            _ => [
+                metas,
+                vec![AccountMeta::new(
+                    Pubkey::new_from_array(crate::rent_collector::RENT_COLLECTOR),
+                    false,
+                )],
+            ]
+            .concat(),
+        };
+
+        metas
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AccountOrderError {
+    WrongCount {
+        expected: usize,
+        found: usize,
+    },
+    Mismatch {
+        index: usize,
+        expected: AccountMeta,
+        found: AccountMeta,
+    },
+}
+
+/*
+    Check an instruction's accounts against the keys it's meant to use before submitting it, to
+    catch accounts in the wrong order or with the wrong flags (e.g. split and refund swapped)
+    without paying for, and exposing part of the key in, a failed spend. Returns the first account
+    that differs from what the program expects.
+*/
+pub fn check_account_order(
+    accounts: &[AccountMeta],
+    expected: &VaultAccounts,
+) -> Result<(), AccountOrderError> {
+    let expected = expected.to_account_metas();
+    if accounts.len() != expected.len() {
+        return Err(AccountOrderError::WrongCount {
+            expected: expected.len(),
+            found: accounts.len(),
+        });
+    }
+
+    match accounts
+        .iter()
+        .zip(expected)
+        .enumerate()
+        .find(|(_, (found, expected))| *found != expected)
+    {
+        Some((index, (found, expected))) => Err(AccountOrderError::Mismatch {
+            index,
+            expected,
+            found: found.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+// SPL Token program, associated token account program and wrapped SOL mint
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+pub const NATIVE_MINT: Pubkey =
+    Pubkey::from_str_const("So11111111111111111111111111111111111111112");
+
+/*
+    To close into wrapped SOL, sign the close with the owner's wSOL associated token account as
+    the refund account, and the signature commits to it like to any other refund. The token
+    account must exist first (it can't be created next to the 896-byte signature, see Transaction
+    Size in the README), so create it beforehand with `create_wsol_account_instruction`. Its
+    token balance only follows the credited lamports once synced: pass TOKEN_PROGRAM_ID as the
+    close's last account to sync it on-chain, or add `sync_native_instruction` after the close.
+*/
+pub fn wsol_account(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), NATIVE_MINT.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+// Create the owner's wSOL account unless it already exists (CreateIdempotent)
+pub fn create_wsol_account_instruction(payer: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(wsol_account(owner), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(NATIVE_MINT, false),
+            AccountMeta::new_readonly(Pubkey::default(), false), // system program
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: vec![1],
+    }
+}
+
+// Bring a wSOL account's token balance in line with its lamports (SyncNative)
+pub fn sync_native_instruction(account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![AccountMeta::new(*account, false)],
+        data: vec![17],
+    }
+}
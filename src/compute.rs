@@ -0,0 +1,22 @@
+use pinocchio::{msg, ProgramResult};
+
+use crate::error::VaultError;
+
+/*
+    Below this many remaining compute units a winternitz recovery can't finish: even a digest far
+    cheaper than the average (~550k) needs more. Checking up front turns the runtime's opaque
+    "exceeded CUs meter" abort into an error and a log telling the client what to change.
+*/
+pub const RECOVER_MIN_CU: u64 = 300_000;
+
+pub fn check_recover_budget() -> ProgramResult {
+    // The syscall only exists on-chain, off-chain callers (e.g. dispatch tests) aren't metered
+    #[cfg(target_os = "solana")]
+    if unsafe { pinocchio::syscalls::sol_remaining_compute_units() } < RECOVER_MIN_CU {
+        msg!("Not enough compute units left to verify the winternitz signature");
+        msg!("Raise the limit with SetComputeUnitLimit, see SPLIT_CU and CLOSE_CU");
+        return Err(VaultError::InsufficientComputeBudget.into());
+    }
+
+    Ok(())
+}
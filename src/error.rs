@@ -0,0 +1,41 @@
+use pinocchio::program_error::ProgramError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultError {
+    InvalidVaultState,         // vault account data has an unexpected length
+    InvalidDiscriminator,      // vault account data doesn't start with the vault discriminator
+    KeyNotBurned,              // vault key hasn't been burned, so its rent can't be reclaimed
+    VaultNotEmpty,             // vault holds more than its rent-exempt minimum
+    InvalidRentCollector,      // rent collector is neither the opener nor the configured collector
+    AccountAliasing,           // the same account was passed for two roles that must be distinct
+    InvalidCommitment,         // not the vault's canonical commitment, or not made by this account
+    UnsupportedAbiVersion,     // instruction's ABI version isn't one this program speaks
+    VaultAlreadyExists,        // open targets an address that already holds lamports or data
+    MessageTooLong,            // spend message has more recipients than fit in a SpendMessage
+    InsufficientFunds,         // spend amount exceeds the vault's balance
+    VaultAlreadyClosed,        // vault account holds no lamports: already closed or never opened
+    UnsupportedStateVersion,   // vault data uses a layout version this program can't read
+    NonCanonicalBump,          // open was given a bump other than the canonical one for the hash
+    InvalidAuthorization,      // authorization doesn't match the recipient, refund or payer
+    InsufficientComputeBudget, // too few compute units left to recover a winternitz signature
+    RecipientNotRentExempt,    // a recipient created by the spend would hold less than its rent
+    CommitmentNotExpired,      // a commitment can only be cancelled once it has expired
+}
+
+impl From<VaultError> for ProgramError {
+    fn from(e: VaultError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+/*
+    Pinocchio fails a borrow when the account is already borrowed. Within one instruction that only
+    happens when the same account is passed for two roles that are borrowed at the same time, so
+    report it as aliasing rather than the generic AccountBorrowFailed.
+*/
+pub fn map_borrow_error(error: ProgramError) -> ProgramError {
+    match error {
+        ProgramError::AccountBorrowFailed => VaultError::AccountAliasing.into(),
+        error => error,
+    }
+}
@@ -0,0 +1,86 @@
+use pinocchio::{log::sol_log_data, pubkey::Pubkey};
+
+/*
+    Events in the format Anchor's event parsers read: one `sol_log_data` call, logged as
+    "Program data: <base64>", carrying an 8-byte discriminator followed by the borsh encoding of
+    the event's fields. The discriminator is the first 8 bytes of SHA-256("event:<EventName>").
+    Fields are encoded in the order they're declared here, and that order is part of the format:
+    new fields may only be appended.
+*/
+
+pub struct VaultOpened {
+    pub vault: Pubkey,
+    pub hash: [u8; 32], // merklized winternitz pubkey the vault is derived from
+    pub opener: Pubkey,
+}
+
+impl VaultOpened {
+    // SHA-256("event:VaultOpened")[..8]
+    pub const DISCRIMINATOR: [u8; 8] = [198, 250, 195, 25, 26, 107, 197, 16];
+    pub const LEN: usize = 8 + 32 + 32 + 32;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[..8].copy_from_slice(&Self::DISCRIMINATOR);
+        data[8..40].copy_from_slice(&self.vault);
+        data[40..72].copy_from_slice(&self.hash);
+        data[72..].copy_from_slice(&self.opener);
+        data
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+}
+
+pub struct VaultSplit {
+    pub vault: Pubkey,
+    pub split: Pubkey,
+    pub refund: Pubkey,
+    pub amount: u64, // lamports paid to the split account
+}
+
+impl VaultSplit {
+    // SHA-256("event:VaultSplit")[..8]
+    pub const DISCRIMINATOR: [u8; 8] = [92, 69, 135, 72, 30, 240, 112, 35];
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[..8].copy_from_slice(&Self::DISCRIMINATOR);
+        data[8..40].copy_from_slice(&self.vault);
+        data[40..72].copy_from_slice(&self.split);
+        data[72..104].copy_from_slice(&self.refund);
+        data[104..].copy_from_slice(&self.amount.to_le_bytes());
+        data
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+}
+
+pub struct VaultClosed {
+    pub vault: Pubkey,
+    pub refund: Pubkey,
+    pub amount: u64, // lamports paid to the refund account
+}
+
+impl VaultClosed {
+    // SHA-256("event:VaultClosed")[..8]
+    pub const DISCRIMINATOR: [u8; 8] = [238, 129, 38, 228, 227, 118, 249, 215];
+    pub const LEN: usize = 8 + 32 + 32 + 8;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[..8].copy_from_slice(&Self::DISCRIMINATOR);
+        data[8..40].copy_from_slice(&self.vault);
+        data[40..72].copy_from_slice(&self.refund);
+        data[72..].copy_from_slice(&self.amount.to_le_bytes());
+        data
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+}
@@ -0,0 +1,114 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::{map_borrow_error, VaultError},
+    state::{Commitment, VaultState},
+    vault::{credit, Vault},
+};
+
+/*
+    Lets the opener take back the rent of a vault that was opened but never funded, without a
+    winternitz signature (e.g. because the key was lost before anything was deposited). Only valid
+    while the vault holds nothing but its rent, so it can never touch deposited funds.
+
+    Given a commitment and its vault address instead, it cancels the commitment once it has
+    expired, which clears a vault address someone squatted on with CommitOpen. Anyone can do that.
+    The commitment's rent goes to the vault address, never back to the committer, so squatting on
+    an address costs a commitment's rent every time it expires.
+*/
+pub struct CancelUnfundedAccounts<'a> {
+    pub vault: &'a AccountInfo,  // unfunded vault holding only its rent, or a commitment (mutable)
+    pub opener: &'a AccountInfo, // opener of the vault, or the committed vault address (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CancelUnfundedAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [vault, opener] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { vault, opener })
+    }
+}
+
+pub struct CancelUnfunded<'a> {
+    pub accounts: CancelUnfundedAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for CancelUnfunded<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        if !data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = CancelUnfundedAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> CancelUnfunded<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &9;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_writable(&[self.accounts.vault, self.accounts.opener])?;
+
+        let is_commitment =
+            self.accounts.vault.try_borrow_data()?.first() == Some(&Commitment::DISCRIMINATOR);
+        if is_commitment {
+            return self.cancel_commitment();
+        }
+
+        if !self.accounts.opener.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        // A vault with no opener (see VaultState::NO_OPENER) has no one to cancel it
+        if vault.state.opener == VaultState::NO_OPENER
+            || vault.state.opener.ne(self.accounts.opener.key())
+        {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        // Anything above the rent was deposited and belongs to the key holder
+        let rent = Rent::get()?.minimum_balance(VaultState::LEN);
+        if vault.balance() > rent {
+            return Err(VaultError::VaultNotEmpty.into());
+        }
+
+        vault.close_to(self.accounts.opener)
+    }
+
+    fn cancel_commitment(&self) -> ProgramResult {
+        let commitment = Commitment::try_from(&self.accounts.vault.try_borrow_data()?[..])?;
+
+        if commitment.vault.ne(self.accounts.opener.key()) {
+            return Err(VaultError::InvalidCommitment.into());
+        }
+
+        // Not even the committer can cancel early: cancelling and committing again would hold
+        // the address for free
+        if !commitment.is_expired(Clock::get()?.slot) {
+            return Err(VaultError::CommitmentNotExpired.into());
+        }
+
+        credit(self.accounts.opener, self.accounts.vault.lamports())?;
+        self.accounts.vault.close().map_err(map_borrow_error)
+    }
+}
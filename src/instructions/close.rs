@@ -1,52 +1,116 @@
-use std::mem::MaybeUninit;
-
 use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_token::instructions::SyncNative;
 use solana_winternitz::signature::WinternitzSignature;
 
+use crate::{
+    error::VaultError,
+    vault::{split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN},
+};
+
+/*
+    Closing into wrapped SOL: pass the token program after the other accounts and sign the close
+    with a wSOL token account as the refund account. The signature commits to the token account
+    like to any other refund, and a SyncNative CPI makes its token balance reflect the credited
+    lamports. The token account must exist already; it can't be created next to the signature.
+*/
 pub struct CloseVaultAccounts<'a> {
     pub vault: &'a AccountInfo,
     pub refund: &'a AccountInfo,
+    #[cfg(feature = "rent-collector")]
+    pub collector: &'a AccountInfo,
+    pub token_program: Option<&'a AccountInfo>, // syncs a wSOL refund account when given
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for CloseVaultAccounts<'a> {
     type Error = ProgramError;
 
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-        let [vault, refund] = accounts else {
-            return Err(ProgramError::NotEnoughAccountKeys);
+        #[cfg(not(feature = "rent-collector"))]
+        let (vault, refund, token_program) = match accounts {
+            [vault, refund] => (vault, refund, None),
+            [vault, refund, token_program] => (vault, refund, Some(token_program)),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
         };
+        #[cfg(feature = "rent-collector")]
+        let (vault, refund, collector, token_program) = match accounts {
+            [vault, refund, collector] => (vault, refund, collector, None),
+            [vault, refund, collector, token_program] => {
+                (vault, refund, collector, Some(token_program))
+            }
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(collector)?;
+
+        if let Some(token_program) = token_program {
+            if token_program.key().ne(&pinocchio_token::ID) {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            // Only a token account can be synced; anything else is a plain close
+            if !refund.is_owned_by(&pinocchio_token::ID) {
+                return Err(ProgramError::InvalidAccountOwner);
+            }
+        }
+
+        // A closed (or never opened) vault is an empty system account
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
 
-        Ok(Self { vault, refund })
+        if !vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if !vault.is_writable() || !refund.is_writable() {
+            return Err(ProgramError::Immutable);
+        }
+
+        // The refund credit would be wiped out when the vault is closed
+        if vault.key() == refund.key() {
+            return Err(VaultError::AccountAliasing.into());
+        }
+
+        Ok(Self {
+            vault,
+            refund,
+            #[cfg(feature = "rent-collector")]
+            collector,
+            token_program,
+        })
     }
 }
 
 pub struct CloseVaultInstructionData {
     pub signature: WinternitzSignature,
     pub bump: [u8; 1],
+    pub salt: Salt, // passphrase hash mixed into the vault seed (all zero for none)
+}
+
+impl CloseVaultInstructionData {
+    pub const BUMP_LEN: usize = 1;
+    // signature + bump (+ salt under `passphrase-salt`)
+    pub const LEN: usize = SIGNATURE_LEN + Self::BUMP_LEN + Salt::LEN;
 }
 
 impl<'a> TryFrom<&'a [u8]> for CloseVaultInstructionData {
     type Error = ProgramError;
 
     fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-        if data.len() != core::mem::size_of::<CloseVaultInstructionData>() {
+        if data.len() != Self::LEN {
             return Err(ProgramError::InvalidInstructionData);
         };
 
-        let mut signature_array = MaybeUninit::<[u8; 896]>::uninit();
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                data[0..896].as_ptr(),
-                signature_array.as_mut_ptr() as *mut u8,
-                896,
-            );
-        }
+        let (data, salt) = Salt::split_off(data)?;
+
+        let (signature, bump) = split_signature(data)?;
 
         Ok(Self {
-            signature: WinternitzSignature::from(unsafe { signature_array.assume_init() }),
-            bump: data[896..897]
+            signature,
+            bump: bump
                 .try_into()
                 .map_err(|_| ProgramError::InvalidInstructionData)?,
+            salt,
         })
     }
 }
@@ -71,27 +135,57 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for CloseVault<'a> {
 
 impl<'a> CloseVault<'a> {
     pub const DISCRIMINATOR: &'a u8 = &2;
+    // [discriminator | refund]
+    pub const MESSAGE_LEN: usize = 1 + 32;
 
     pub fn process(&self) -> ProgramResult {
-        // Recover our pubkey hash from the signature
-        let hash = self
-            .instruction_data
-            .signature
-            .recover_pubkey(self.accounts.refund.key())
-            .merklize();
-        // Fast PDA equivalence check
-        if solana_nostd_sha256::hashv(&[
-            hash.as_ref(),
-            self.instruction_data.bump.as_ref(),
-            crate::ID.as_ref(),
-            b"ProgramDerivedAddress",
-        ])
-        .ne(self.accounts.vault.key())
-        {
-            return Err(ProgramError::MissingRequiredSignature);
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_spend(
+            self.accounts.vault,
+            &self.instruction_data.bump,
+            &[self.accounts.refund],
+        )?;
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        // assemble our close message: discriminator followed by the refund pubkey
+        let mut message = [0u8; Self::MESSAGE_LEN];
+        message[0] = *Self::DISCRIMINATOR;
+        message[1..].clone_from_slice(self.accounts.refund.key());
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            &message,
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        // Any collected rent goes to the collector, the rest of the balance to Refund account
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        #[cfg(feature = "anchor-events")]
+        crate::events::VaultClosed {
+            vault: *self.accounts.vault.key(),
+            refund: *self.accounts.refund.key(),
+            amount: vault.balance(),
         }
-        // Close Vault and refund balance to Refund account
-        *self.accounts.refund.try_borrow_mut_lamports()? += self.accounts.vault.lamports();
-        self.accounts.vault.close()
+        .emit();
+
+        vault.close_to(self.accounts.refund)?;
+
+        // The token balance only follows the lamports once synced, so this CPI has to come last
+        if self.accounts.token_program.is_some() {
+            SyncNative {
+                native_token: self.accounts.refund,
+            }
+            .invoke()?;
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,238 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    error::VaultError,
+    message::{entries_hash, MAX_HASHED_ENTRIES},
+    split_bps::SplitBps,
+    vault::{check_recipient, split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN},
+};
+
+/*
+    Close a vault to several refund accounts by committed proportions, e.g. to divide an
+    inheritance vault between heirs. Each share is given in basis points and the shares must add
+    up to 10,000. Every account but the last gets its share of the balance rounded down; the last
+    one gets whatever is left, so rounding never strands lamports in the vault.
+
+    The signed message is [discriminator | shares hash], where the shares hash is the SHA-256 of
+    every (bps, refund) pair in account order, so the message size doesn't depend on the number
+    of refund accounts.
+*/
+pub struct CloseProportionalAccounts<'a> {
+    pub vault: &'a AccountInfo, // vault to close (mutable)
+    #[cfg(feature = "rent-collector")]
+    pub collector: &'a AccountInfo, // Receives the vault's rent-exempt reserve (mutable)
+    pub refunds: &'a [AccountInfo], // one account per share, in the same order (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CloseProportionalAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        #[cfg(not(feature = "rent-collector"))]
+        let [vault, refunds @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        let [vault, collector, refunds @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(collector)?;
+
+        if refunds.is_empty() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
+
+        // Credits to the vault itself would be wiped out when it's closed
+        if refunds.iter().any(|refund| refund.key() == vault.key()) {
+            return Err(VaultError::AccountAliasing.into());
+        }
+
+        Ok(Self {
+            vault,
+            #[cfg(feature = "rent-collector")]
+            collector,
+            refunds,
+        })
+    }
+}
+
+pub struct CloseProportionalInstructionData<'a> {
+    pub signature: WinternitzSignature,
+    pub bump: [u8; 1],
+    pub shares: &'a [u8], // one little-endian u16 (basis points) per refund account
+    pub salt: Salt,       // passphrase hash for the vault seed (all zero for none)
+}
+
+impl CloseProportionalInstructionData<'_> {
+    pub const BUMP_LEN: usize = 1;
+    pub const SHARE_LEN: usize = 2;
+    // signature + bump + a share for every refund account (+ salt)
+    pub const MAX_LEN: usize =
+        SIGNATURE_LEN + Self::BUMP_LEN + MAX_HASHED_ENTRIES * Self::SHARE_LEN + Salt::LEN;
+
+    pub fn share(&self, index: usize) -> u16 {
+        let offset = index * Self::SHARE_LEN;
+        u16::from_le_bytes([self.shares[offset], self.shares[offset + 1]])
+    }
+
+    pub fn len(&self) -> usize {
+        self.shares.len() / Self::SHARE_LEN
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shares.is_empty()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for CloseProportionalInstructionData<'a> {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        // The salt (if any) follows the shares
+        let (data, salt) = Salt::split_off(data)?;
+        if data.len() < SIGNATURE_LEN + Self::BUMP_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (signature, rest) = split_signature(data)?;
+        let (bump, shares) = rest.split_at(Self::BUMP_LEN);
+
+        if shares.len() % Self::SHARE_LEN != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // The shares must add up to the whole balance
+        let total: u32 = shares
+            .chunks_exact(Self::SHARE_LEN)
+            .map(|share| u16::from_le_bytes([share[0], share[1]]) as u32)
+            .sum();
+        if total != SplitBps::MAX_BPS as u32 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            shares,
+            salt,
+        })
+    }
+}
+
+pub struct CloseProportional<'a> {
+    pub accounts: CloseProportionalAccounts<'a>,
+    pub instruction_data: CloseProportionalInstructionData<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for CloseProportional<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = CloseProportionalAccounts::try_from(accounts)?;
+        let instruction_data = CloseProportionalInstructionData::try_from(data)?;
+
+        // Every refund account needs exactly one share
+        if instruction_data.len() != accounts.refunds.len() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> CloseProportional<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &15;
+
+    pub const MAX_REFUNDS: usize = MAX_HASHED_ENTRIES;
+
+    // SHA-256 over [bps | refund] for each refund account in order (see `entries_hash`)
+    pub fn shares_hash<'b>(
+        shares: &'b [u8],
+        refunds: impl IntoIterator<Item = &'b Pubkey>,
+    ) -> Result<[u8; 32], VaultError> {
+        entries_hash(shares, CloseProportionalInstructionData::SHARE_LEN, refunds)
+    }
+
+    // The signed message: [discriminator | shares hash]
+    pub fn message(shares_hash: &[u8; 32]) -> [u8; 33] {
+        let mut message = [0u8; 33];
+        message[0] = *Self::DISCRIMINATOR;
+        message[1..].copy_from_slice(shares_hash);
+        message
+    }
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        {
+            crate::strict::check_spend(self.accounts.vault, &self.instruction_data.bump, &[])?;
+            for refund in self.accounts.refunds {
+                crate::strict::check_writable(&[refund])?;
+            }
+        }
+
+        if self.accounts.refunds.len() > Self::MAX_REFUNDS {
+            return Err(VaultError::MessageTooLong.into());
+        }
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        // The collected rent is not part of the balance being divided
+        let balance = vault.spendable()?;
+
+        let (last, others) = self
+            .accounts
+            .refunds
+            .split_last()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let mut paid = 0;
+        for (index, refund) in others.iter().enumerate() {
+            let amount = SplitBps::share(balance, self.instruction_data.share(index));
+            check_recipient(refund, amount)?;
+            paid += amount;
+        }
+        check_recipient(last, balance - paid)?;
+
+        let shares_hash = Self::shares_hash(
+            self.instruction_data.shares,
+            self.accounts.refunds.iter().map(|refund| refund.key()),
+        )?;
+        let message = Self::message(&shares_hash);
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            &message,
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        for (index, refund) in others.iter().enumerate() {
+            vault.transfer(
+                refund,
+                SplitBps::share(balance, self.instruction_data.share(index)),
+            )?;
+        }
+
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        // The last account takes the rest, rounding included
+        vault.close_to(last)
+    }
+}
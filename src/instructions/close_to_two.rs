@@ -0,0 +1,144 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    error::VaultError,
+    state::VaultState,
+    vault::{split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN},
+};
+
+/*
+    Close a vault while routing the deposited funds and the reclaimed rent to different accounts,
+    e.g. funds to a cold wallet and rent back to the hot wallet that opened the vault.
+    Both destinations are committed in the signed message.
+*/
+pub struct CloseToTwoAccounts<'a> {
+    pub vault: &'a AccountInfo,       // vault to close (mutable)
+    pub funds: &'a AccountInfo,       // recipient of everything above the rent-exempt minimum (mutable)
+    pub rent_refund: &'a AccountInfo, // recipient of the vault's rent-exempt minimum (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CloseToTwoAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [vault, funds, rent_refund] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        // With a rent collector configured, the rent can't be sent anywhere else
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(rent_refund)?;
+
+        // A closed (or never opened) vault is an empty system account
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
+
+        // Either credit would be wiped out when the vault is closed
+        if vault.key() == funds.key() || vault.key() == rent_refund.key() {
+            return Err(VaultError::AccountAliasing.into());
+        }
+
+        Ok(Self {
+            vault,
+            funds,
+            rent_refund,
+        })
+    }
+}
+
+pub struct CloseToTwoInstructionData {
+    pub signature: WinternitzSignature,
+    pub bump: [u8; 1],
+    pub salt: Salt, // passphrase hash for the vault seed (all zero for none)
+}
+
+impl CloseToTwoInstructionData {
+    pub const BUMP_LEN: usize = 1;
+    // signature + bump (+ salt under `passphrase-salt`)
+    pub const LEN: usize = SIGNATURE_LEN + Self::BUMP_LEN + Salt::LEN;
+}
+
+impl<'a> TryFrom<&'a [u8]> for CloseToTwoInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        };
+
+        let (data, salt) = Salt::split_off(data)?;
+
+        let (signature, bump) = split_signature(data)?;
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            salt,
+        })
+    }
+}
+
+pub struct CloseToTwo<'a> {
+    pub accounts: CloseToTwoAccounts<'a>,
+    pub instruction_data: CloseToTwoInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for CloseToTwo<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = CloseToTwoAccounts::try_from(accounts)?;
+        let instruction_data = CloseToTwoInstructionData::try_from(data)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> CloseToTwo<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &3;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_spend(
+            self.accounts.vault,
+            &self.instruction_data.bump,
+            &[self.accounts.funds, self.accounts.rent_refund],
+        )?;
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        // assemble our message: discriminator, funds pubkey, then rent refund pubkey
+        let mut message = [0u8; 65];
+        message[0] = *Self::DISCRIMINATOR;
+        message[1..33].clone_from_slice(self.accounts.funds.key());
+        message[33..].clone_from_slice(self.accounts.rent_refund.key());
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            &message,
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        // Rent goes back to the rent refund account, everything above it to the funds account
+        let rent = Rent::get()?
+            .minimum_balance(VaultState::LEN)
+            .min(vault.balance());
+
+        vault.transfer(self.accounts.rent_refund, rent)?;
+        vault.close_to(self.accounts.funds)
+    }
+}
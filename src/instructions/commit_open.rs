@@ -0,0 +1,159 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::find_program_address,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::{CreateAccount, Transfer};
+
+use crate::{
+    error::VaultError,
+    state::{Commitment, VaultState},
+};
+
+/*
+    First half of a front-running resistant open. Vault addresses are deterministic, so anyone who
+    sees a plain open's hash in flight can race it. Instead, the opener first commits to the vault
+    address (which doesn't reveal the hash) and later reveals the hash with RevealOpen.
+
+    Committing also pre-funds the vault address with its rent. A system account holding lamports
+    can't be created again, so a plain open of a committed vault fails and only the committer's
+    RevealOpen can initialize it.
+
+    Only the canonical commitment bump is accepted, so every vault address has exactly one
+    commitment and RevealOpen only trusts that one. Someone who squats on a vault address by
+    committing to it first can't reveal without the hash, and their commitment can be cancelled
+    with CancelUnfunded once it expires. Everything they put in stays at the vault address, and
+    once the commitment is gone a plain OpenVault can open the pre-funded address.
+*/
+pub struct CommitOpenAccounts<'a> {
+    pub payer: &'a AccountInfo,      // committer, pays for the commitment and the vault rent (signer, mutable)
+    pub vault: &'a AccountInfo,      // vault address being committed to, not yet opened (mutable)
+    pub commitment: &'a AccountInfo, // commitment PDA ["commitment", vault] to create (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CommitOpenAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [payer, vault, commitment, _system_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        Ok(Self {
+            payer,
+            vault,
+            commitment,
+        })
+    }
+}
+
+pub struct CommitOpenInstructionData {
+    pub bump: [u8; 1], // canonical commitment PDA derivation bump passed from client (u8)
+}
+
+impl CommitOpenInstructionData {
+    // 1-byte bump (2 bytes including the discriminator)
+    pub const LEN: usize = 1;
+}
+
+impl<'a> TryFrom<&'a [u8]> for CommitOpenInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let bump = data[0..1]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        Ok(Self { bump })
+    }
+}
+
+pub struct CommitOpen<'a> {
+    pub accounts: CommitOpenAccounts<'a>,
+    pub instruction_data: CommitOpenInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for CommitOpen<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let instruction_data = CommitOpenInstructionData::try_from(data)?;
+        let accounts = CommitOpenAccounts::try_from(accounts)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> CommitOpen<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &7;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        {
+            crate::strict::check_signer(self.accounts.payer)?;
+            crate::strict::check_writable(&[self.accounts.vault, self.accounts.commitment])?;
+        }
+
+        // Same as a vault: only the canonical bump
+        let (_, canonical_bump) =
+            find_program_address(&[Commitment::SEED, self.accounts.vault.key()], &crate::ID);
+        if self.instruction_data.bump != [canonical_bump] {
+            return Err(VaultError::NonCanonicalBump.into());
+        }
+
+        let rent = Rent::get()?;
+        let seeds = [
+            Seed::from(Commitment::SEED),
+            Seed::from(self.accounts.vault.key()),
+            Seed::from(&self.instruction_data.bump),
+        ];
+
+        CreateAccount {
+            from: self.accounts.payer,
+            to: self.accounts.commitment,
+            lamports: rent.minimum_balance(Commitment::LEN),
+            space: Commitment::LEN as u64,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        let commitment = Commitment::new(
+            *self.accounts.payer.key(),
+            *self.accounts.vault.key(),
+            Clock::get()?.slot,
+        );
+        self.accounts
+            .commitment
+            .try_borrow_mut_data()?
+            .copy_from_slice(&commitment.to_bytes());
+
+        // Occupy the vault address so it can only be opened through RevealOpen
+        let top_up = rent
+            .minimum_balance(VaultState::LEN)
+            .saturating_sub(self.accounts.vault.lamports());
+        if top_up > 0 {
+            Transfer {
+                from: self.accounts.payer,
+                to: self.accounts.vault,
+                lamports: top_up,
+            }
+            .invoke()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use pinocchio::{
+    account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, ProgramResult,
+};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    close::CloseVault,
+    message::SpendMessage,
+    split::SplitVault,
+    vault::{split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN},
+};
+
+/*
+    Developer tool for integration debugging: reports why a spend would fail, as return data,
+    without touching any account. It takes a signature and the exact message it's supposed to be
+    over. Only built with the `diagnose` feature, so production deployments don't carry it and
+    its discriminator is rejected like an unknown one.
+
+    With no accounts it returns the merklized pubkey hash recovered from the signature. Compare it
+    with the hash you expect to tell a message-construction bug from a signature by the wrong key.
+
+    With a vault account, the bump follows the signature, the salt (under `passphrase-salt`)
+    follows the message, and the return data is a Diagnosis code followed by the recovered hash
+    (all zero when the checks stop before recovering it). The vault and PDA checks are the ones
+    the spends make. Split and close messages are also checked against their signed length, and a
+    split's amount against the vault's spendable balance (less the collected rent under
+    `rent-collector`); any other message only gets the vault and PDA checks.
+*/
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diagnosis {
+    Ok = 0,                   // the spend would pass signature verification and be affordable
+    BadLength = 1,            // empty, or not the length its discriminator signs
+    ZeroHash = 2,             // the vault account records no pubkey hash: unopened or not a vault
+    PdaMismatch = 3,          // the recovered hash and the bump don't derive the vault
+    AmountExceedsBalance = 4, // a split of more than the vault can spend
+}
+
+pub struct DiagnoseInstructionData<'a> {
+    pub signature: WinternitzSignature,
+    pub bump: Option<[u8; 1]>, // vault PDA derivation bump, only when a vault is given
+    pub message: &'a [u8],     // exact message the signature is supposed to be over
+    pub salt: Salt,            // passphrase hash for the vault seed, only when a vault is given
+}
+
+impl<'a> DiagnoseInstructionData<'a> {
+    fn parse(data: &'a [u8], with_bump: bool) -> Result<Self, ProgramError> {
+        let (data, salt) = if with_bump {
+            Salt::split_off(data)?
+        } else {
+            (data, Salt::default())
+        };
+
+        let header_len = SIGNATURE_LEN + with_bump as usize;
+        if data.len() < header_len {
+            return Err(ProgramError::InvalidInstructionData);
+        };
+
+        let (signature, rest) = split_signature(data)?;
+
+        Ok(Self {
+            signature,
+            bump: with_bump.then(|| [rest[0]]),
+            message: &rest[with_bump as usize..],
+            salt,
+        })
+    }
+}
+
+pub struct Diagnose<'a> {
+    pub vault: Option<&'a AccountInfo>,
+    pub instruction_data: DiagnoseInstructionData<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Diagnose<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let vault = match accounts {
+            [] => None,
+            [vault] => Some(vault),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+        let instruction_data = DiagnoseInstructionData::parse(data, vault.is_some())?;
+
+        Ok(Self {
+            vault,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> Diagnose<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &6;
+
+    pub fn process(&self) -> ProgramResult {
+        let (Some(vault), Some(bump)) = (self.vault, self.instruction_data.bump) else {
+            let hash = RecoveredVault::recover(
+                &self.instruction_data.signature,
+                self.instruction_data.message,
+            )?;
+            set_return_data(&hash);
+            return Ok(());
+        };
+
+        let (diagnosis, hash) = self.diagnose(vault, &bump)?;
+
+        let mut report = [0u8; 33];
+        report[0] = diagnosis as u8;
+        report[1..].copy_from_slice(&hash);
+        set_return_data(&report);
+
+        Ok(())
+    }
+
+    // The first check the spend would fail, with the recovered hash if it got that far
+    fn diagnose(
+        &self,
+        vault: &AccountInfo,
+        bump: &[u8; 1],
+    ) -> Result<(Diagnosis, [u8; 32]), ProgramError> {
+        let message = self.instruction_data.message;
+
+        let signed_len = match message.first() {
+            None => return Ok((Diagnosis::BadLength, [0; 32])),
+            Some(discriminator) if discriminator == SplitVault::DISCRIMINATOR => {
+                Some(SplitVault::MESSAGE_LEN)
+            }
+            Some(discriminator) if discriminator == CloseVault::DISCRIMINATOR => {
+                Some(CloseVault::MESSAGE_LEN)
+            }
+            Some(_) => None,
+        };
+        if signed_len.is_some_and(|len| len != message.len()) {
+            return Ok((Diagnosis::BadLength, [0; 32]));
+        }
+
+        // The same vault checks a spend makes, plus a recorded hash
+        let vault = match Vault::try_from(vault) {
+            Ok(vault) if vault.state.hash != [0; 32] => vault,
+            _ => return Ok((Diagnosis::ZeroHash, [0; 32])),
+        };
+
+        let hash = RecoveredVault::recover(&self.instruction_data.signature, message)?;
+        let seed = self.instruction_data.salt.seed(&hash);
+        if RecoveredVault::check(seed, bump, vault.account).is_err() {
+            return Ok((Diagnosis::PdaMismatch, hash));
+        }
+
+        // Affordable means what split itself checks: within the spendable balance
+        if message[0] == *SplitVault::DISCRIMINATOR {
+            let offset = SpendMessage::recipient_offset(0);
+            let mut amount = [0u8; 8];
+            amount.copy_from_slice(&message[offset..offset + 8]);
+            if u64::from_le_bytes(amount) > vault.spendable()? {
+                return Ok((Diagnosis::AmountExceedsBalance, hash));
+            }
+        }
+
+        Ok((Diagnosis::Ok, hash))
+    }
+}
@@ -0,0 +1,117 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::{map_borrow_error, VaultError},
+    state::Authorization,
+    vault::credit,
+};
+
+/*
+    Second half of a pre-authorized split (see StoreAuthorization). Until the expiry slot only the
+    recipient can execute: it receives the authorized amount, the refund account the rest of the
+    vault's funds and the payer the authorization's rent. From the expiry slot on anyone can
+    execute, and the refund account gets the amount too, so an absent recipient can't lock the
+    funds. No winternitz signature is needed, it was verified when stored.
+    A rent collector gets nothing here: the vault's rent was collected when it was closed by
+    StoreAuthorization, and the authorization's rent was fronted by the payer.
+*/
+pub struct ExecuteAuthorizationAccounts<'a> {
+    pub recipient: &'a AccountInfo,     // receives amount (signer until expiry, mutable)
+    pub authorization: &'a AccountInfo, // authorization made out to the recipient (mutable)
+    pub refund: &'a AccountInfo,        // refund account recorded in the authorization (mutable)
+    pub payer: &'a AccountInfo,         // payer recorded in the authorization (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ExecuteAuthorizationAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [recipient, authorization, refund, payer] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !authorization.is_owned_by(&crate::ID) {
+            return Err(VaultError::InvalidAuthorization.into());
+        }
+
+        Ok(Self {
+            recipient,
+            authorization,
+            refund,
+            payer,
+        })
+    }
+}
+
+pub struct ExecuteAuthorization<'a> {
+    pub accounts: ExecuteAuthorizationAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for ExecuteAuthorization<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        if !data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = ExecuteAuthorizationAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> ExecuteAuthorization<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &11;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_writable(&[
+            self.accounts.recipient,
+            self.accounts.authorization,
+            self.accounts.refund,
+            self.accounts.payer,
+        ])?;
+
+        let authorization =
+            Authorization::try_from(&self.accounts.authorization.try_borrow_data()?[..])?;
+
+        if authorization.recipient.ne(self.accounts.recipient.key())
+            || authorization.refund.ne(self.accounts.refund.key())
+            || authorization.payer.ne(self.accounts.payer.key())
+        {
+            return Err(VaultError::InvalidAuthorization.into());
+        }
+
+        // Once expired the amount goes back to the refund account with the rest
+        let expired = authorization.is_expired(Clock::get()?.slot);
+        if !expired && !self.accounts.recipient.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let payee = if expired {
+            self.accounts.refund
+        } else {
+            self.accounts.recipient
+        };
+
+        // The rent was paid by the payer, the rest is the vault's funds
+        let balance = self.accounts.authorization.lamports();
+        let rent = Rent::get()?
+            .minimum_balance(Authorization::LEN)
+            .min(balance);
+        let remainder = (balance - rent)
+            .checked_sub(authorization.amount)
+            .ok_or(VaultError::InsufficientFunds)?;
+
+        credit(payee, authorization.amount)?;
+        credit(self.accounts.refund, remainder)?;
+        credit(self.accounts.payer, rent)?;
+
+        self.accounts.authorization.close().map_err(map_borrow_error)
+    }
+}
@@ -1,3 +1,17 @@
+pub mod cancel_unfunded;
 pub mod close;
+pub mod close_proportional;
+pub mod close_to_two;
+pub mod commit_open;
+#[cfg(feature = "diagnose")]
+pub mod diagnose;
+pub mod execute_authorization;
 pub mod open;
+pub mod prove;
+pub mod reclaim;
+pub mod reveal_open;
+pub mod rotate;
 pub mod split;
+pub mod split_bps;
+pub mod split_many;
+pub mod store_authorization;
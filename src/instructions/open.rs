@@ -2,25 +2,44 @@ use pinocchio::{
     account_info::AccountInfo,
     instruction::{Seed, Signer},
     program_error::ProgramError,
+    pubkey::find_program_address,
     sysvars::{rent::Rent, Sysvar},
     ProgramResult,
 };
-use pinocchio_system::instructions::CreateAccount;
+use pinocchio_system::instructions::{Allocate, Assign, CreateAccount, Transfer};
 
+use crate::{
+    error::VaultError,
+    state::{Commitment, VaultState},
+    vault::vault_signer_seeds,
+};
+
+/*
+    An address that already holds lamports (sent there by mistake, or left by a cancelled
+    commitment) can still be opened: pass the vault's commitment PDA as well, to show that no
+    commitment currently holds the address for someone else.
+*/
 pub struct OpenVaultAccounts<'a> {
     pub payer: &'a AccountInfo,
     pub vault: &'a AccountInfo,
+    pub commitment: Option<&'a AccountInfo>, // commitment PDA ["commitment", vault], if pre-funded
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for OpenVaultAccounts<'a> {
     type Error = ProgramError;
 
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-        let [payer, vault, _system_program] = accounts else {
-            return Err(ProgramError::NotEnoughAccountKeys);
+        let (payer, vault, commitment) = match accounts {
+            [payer, vault, _system_program] => (payer, vault, None),
+            [payer, vault, _system_program, commitment] => (payer, vault, Some(commitment)),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
         };
 
-        Ok(Self { payer, vault })
+        Ok(Self {
+            payer,
+            vault,
+            commitment,
+        })
     }
 }
 
@@ -29,11 +48,16 @@ pub struct OpenVaultInstructionData {
     pub bump: [u8; 1],  // PDA derivation bump passed from client (u8)
 }
 
+impl OpenVaultInstructionData {
+    // 32-byte hash + 1-byte bump (34 bytes including the discriminator)
+    pub const LEN: usize = 32 + 1;
+}
+
 impl<'a> TryFrom<&'a [u8]> for OpenVaultInstructionData {
     type Error = ProgramError;
 
     fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-        if data.len() != core::mem::size_of::<OpenVaultInstructionData>() {
+        if data.len() != Self::LEN {
             return Err(ProgramError::InvalidInstructionData);
         }
 
@@ -71,19 +95,117 @@ impl<'a> OpenVault<'a> {
     pub const DISCRIMINATOR: &'a u8 = &0;
 
     pub fn process(&self) -> ProgramResult {
-        let lamports = Rent::get()?.minimum_balance(0);
-        let seeds = [
-            Seed::from(&self.instruction_data.hash),
-            Seed::from(&self.instruction_data.bump),
-        ];
-
-        CreateAccount {
-            from: self.accounts.payer,
-            to: self.accounts.vault,
-            lamports,
-            space: 0,
-            owner: &crate::ID,
+        #[cfg(feature = "strict-validation")]
+        {
+            crate::strict::check_signer(self.accounts.payer)?;
+            crate::strict::check_writable(&[self.accounts.vault])?;
+        }
+
+        // The system program would fail with an opaque "already in use" on an existing vault, so
+        // say why up front
+        if !self.accounts.vault.is_owned_by(&pinocchio_system::ID)
+            || self.accounts.vault.data_len() != 0
+        {
+            return Err(VaultError::VaultAlreadyExists.into());
+        }
+
+        let prefunded = self.accounts.vault.lamports() != 0;
+        if prefunded {
+            self.check_uncommitted()?;
+        }
+
+        // Only the canonical bump, so each winternitz key maps to exactly one vault address
+        let (_, canonical_bump) = find_program_address(&[&self.instruction_data.hash], &crate::ID);
+        if self.instruction_data.bump != [canonical_bump] {
+            return Err(VaultError::NonCanonicalBump.into());
+        }
+
+        let lamports = Rent::get()?.minimum_balance(VaultState::LEN);
+        let seeds = vault_signer_seeds(&self.instruction_data.hash, &self.instruction_data.bump)
+            .map(Seed::from);
+        let signers = [Signer::from(&seeds)];
+
+        if prefunded {
+            // Only what's missing from the rent, then take over the account as it is
+            let top_up = lamports.saturating_sub(self.accounts.vault.lamports());
+            if top_up > 0 {
+                Transfer {
+                    from: self.accounts.payer,
+                    to: self.accounts.vault,
+                    lamports: top_up,
+                }
+                .invoke()?;
+            }
+
+            allocate_vault(self.accounts.vault, &signers)?;
+        } else {
+            CreateAccount {
+                from: self.accounts.payer,
+                to: self.accounts.vault,
+                lamports,
+                space: VaultState::LEN as u64,
+                owner: &crate::ID,
+            }
+            .invoke_signed(&signers)?;
+        }
+
+        // Record the PDA seeds so the vault can be decoded off-chain, and the payer so the rent
+        // can be handed back to them
+        self.accounts.vault.try_borrow_mut_data()?.copy_from_slice(
+            &VaultState::new(
+                self.instruction_data.hash,
+                self.instruction_data.bump,
+                *self.accounts.payer.key(),
+            )
+            .to_bytes(),
+        );
+
+        #[cfg(feature = "anchor-events")]
+        crate::events::VaultOpened {
+            vault: *self.accounts.vault.key(),
+            hash: self.instruction_data.hash,
+            opener: *self.accounts.payer.key(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    // While a commitment holds the address only its committer can open it, with RevealOpen
+    fn check_uncommitted(&self) -> ProgramResult {
+        let Some(commitment) = self.accounts.commitment else {
+            return Err(VaultError::VaultAlreadyExists.into());
+        };
+
+        let (canonical_commitment, _) =
+            find_program_address(&[Commitment::SEED, self.accounts.vault.key()], &crate::ID);
+        if commitment.key().ne(&canonical_commitment) {
+            return Err(VaultError::InvalidCommitment.into());
         }
-        .invoke_signed(&[Signer::from(&seeds)])
+
+        if commitment.lamports() != 0 {
+            return Err(VaultError::VaultAlreadyExists.into());
+        }
+
+        Ok(())
+    }
+}
+
+/*
+    Make a system account that already holds lamports into a vault: allocate its data and assign
+    it to the program, signing as the vault PDA. Signing also checks that the seeds derive the
+    vault address.
+*/
+pub fn allocate_vault(vault: &AccountInfo, signers: &[Signer]) -> ProgramResult {
+    Allocate {
+        account: vault,
+        space: VaultState::LEN as u64,
+    }
+    .invoke_signed(signers)?;
+
+    Assign {
+        account: vault,
+        owner: &crate::ID,
     }
+    .invoke_signed(signers)
 }
@@ -0,0 +1,126 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    state::VaultState,
+    vault::{split_signature, RecoveredVault, Salt, SIGNATURE_LEN},
+};
+
+/*
+    Prove control of a vault's winternitz key by signing a challenge (e.g. a nonce handed out by
+    an off-chain service) without moving any funds. The transaction succeeding is the proof.
+
+    WARNING: a winternitz key is one-time. Producing this proof reveals part of the private key,
+    so the vault should be split or closed to a fresh vault right after it is used. The vault is
+    marked as burned, which lets its rent be reclaimed once it has been emptied.
+
+    Like every spend, the signed message is prefixed with the instruction discriminator so a proof
+    can never be replayed as another instruction.
+*/
+pub struct ProveOwnershipAccounts<'a> {
+    pub vault: &'a AccountInfo, // vault whose key is being proven (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ProveOwnershipAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [vault] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { vault })
+    }
+}
+
+pub struct ProveOwnershipInstructionData {
+    pub signature: WinternitzSignature,
+    pub bump: [u8; 1],
+    pub challenge: [u8; 32],
+    pub salt: Salt, // passphrase hash for the vault seed (all zero for none)
+}
+
+impl ProveOwnershipInstructionData {
+    pub const BUMP_LEN: usize = 1;
+    pub const CHALLENGE_LEN: usize = 32;
+    // signature + bump + challenge (+ salt under `passphrase-salt`)
+    pub const LEN: usize = SIGNATURE_LEN + Self::BUMP_LEN + Self::CHALLENGE_LEN + Salt::LEN;
+}
+
+impl<'a> TryFrom<&'a [u8]> for ProveOwnershipInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        };
+
+        let (data, salt) = Salt::split_off(data)?;
+        let (signature, rest) = split_signature(data)?;
+        let (bump, challenge) = rest.split_at(Self::BUMP_LEN);
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            challenge: challenge
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            salt,
+        })
+    }
+}
+
+pub struct ProveOwnership<'a> {
+    pub accounts: ProveOwnershipAccounts<'a>,
+    pub instruction_data: ProveOwnershipInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for ProveOwnership<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = ProveOwnershipAccounts::try_from(accounts)?;
+        let instruction_data = ProveOwnershipInstructionData::try_from(data)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> ProveOwnership<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &4;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_spend(self.accounts.vault, &self.instruction_data.bump, &[])?;
+
+        // assemble our proof message: discriminator followed by the challenge
+        let mut message = [0u8; 33];
+        message[0] = *Self::DISCRIMINATOR;
+        message[1..].clone_from_slice(&self.instruction_data.challenge);
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            &message,
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        // The key has now signed a message that doesn't close the vault, so mark it as burned
+        let mut data = self.accounts.vault.try_borrow_mut_data()?;
+        let mut state = VaultState::try_from(&data[..])?;
+        state.burned = true;
+        data.copy_from_slice(&state.to_bytes());
+
+        Ok(())
+    }
+}
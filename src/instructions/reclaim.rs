@@ -0,0 +1,83 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{error::VaultError, state::VaultState, vault::Vault};
+
+/*
+    Permissionless cleanup for vaults that were left open with a burned key (e.g. after an
+    ownership proof) and no longer hold anything but their rent. Without this the rent would be
+    locked forever, since spending again with a burned key isn't safe.
+    The rent goes back to the account that opened the vault.
+*/
+pub struct ReclaimRentAccounts<'a> {
+    pub vault: &'a AccountInfo,     // burned vault holding only its rent (mutable)
+    pub collector: &'a AccountInfo, // opener of the vault, receives the rent (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ReclaimRentAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [vault, collector] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { vault, collector })
+    }
+}
+
+pub struct ReclaimRent<'a> {
+    pub accounts: ReclaimRentAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for ReclaimRent<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        if !data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = ReclaimRentAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> ReclaimRent<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &5;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_writable(&[self.accounts.vault, self.accounts.collector])?;
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        if !vault.is_locked() {
+            return Err(VaultError::KeyNotBurned.into());
+        }
+
+        // A vault with no opener keeps its rent for the key holder
+        if vault.state.opener == VaultState::NO_OPENER
+            || vault.state.opener.ne(self.accounts.collector.key())
+        {
+            return Err(VaultError::InvalidRentCollector.into());
+        }
+
+        // Only rent may be left, anything above it still belongs to the key holder
+        let rent = Rent::get()?.minimum_balance(VaultState::LEN);
+        if vault.balance() > rent {
+            return Err(VaultError::VaultNotEmpty.into());
+        }
+
+        vault.close_to(self.accounts.collector)
+    }
+}
@@ -0,0 +1,122 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::find_program_address,
+    ProgramResult,
+};
+
+use crate::{
+    error::{map_borrow_error, VaultError},
+    open::{allocate_vault, OpenVaultInstructionData},
+    state::{Commitment, VaultState},
+    vault::{credit, vault_signer_seeds},
+};
+
+/*
+    Second half of the front-running resistant open (see CommitOpen). The vault address already
+    holds its rent, so instead of creating the account we allocate its data and assign it to the
+    program, then consume the commitment. Only the committer of the vault's canonical commitment
+    can reveal.
+*/
+pub struct RevealOpenAccounts<'a> {
+    pub payer: &'a AccountInfo,      // committer, receives the commitment's rent back (signer, mutable)
+    pub vault: &'a AccountInfo,      // pre-funded vault address to initialize (mutable)
+    pub commitment: &'a AccountInfo, // commitment PDA ["commitment", vault] made by the payer (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for RevealOpenAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [payer, vault, commitment, _system_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !payer.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if !commitment.is_owned_by(&crate::ID) {
+            return Err(VaultError::InvalidCommitment.into());
+        }
+
+        Ok(Self {
+            payer,
+            vault,
+            commitment,
+        })
+    }
+}
+
+pub struct RevealOpen<'a> {
+    pub accounts: RevealOpenAccounts<'a>,
+    pub instruction_data: OpenVaultInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for RevealOpen<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let instruction_data = OpenVaultInstructionData::try_from(data)?;
+        let accounts = RevealOpenAccounts::try_from(accounts)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> RevealOpen<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &8;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        {
+            crate::strict::check_signer(self.accounts.payer)?;
+            crate::strict::check_writable(&[self.accounts.vault, self.accounts.commitment])?;
+        }
+
+        // Only the canonical commitment PDA counts, whatever else this program has written
+        let (canonical_commitment, _) =
+            find_program_address(&[Commitment::SEED, self.accounts.vault.key()], &crate::ID);
+        if self.accounts.commitment.key().ne(&canonical_commitment) {
+            return Err(VaultError::InvalidCommitment.into());
+        }
+
+        // The commitment is program owned, so its contents were written by CommitOpen
+        let commitment = Commitment::try_from(&self.accounts.commitment.try_borrow_data()?[..])?;
+        if commitment.committer.ne(self.accounts.payer.key())
+            || commitment.vault.ne(self.accounts.vault.key())
+        {
+            return Err(VaultError::InvalidCommitment.into());
+        }
+
+        // Same as a plain open: only the canonical bump
+        let (_, canonical_bump) = find_program_address(&[&self.instruction_data.hash], &crate::ID);
+        if self.instruction_data.bump != [canonical_bump] {
+            return Err(VaultError::NonCanonicalBump.into());
+        }
+
+        // Signing with the vault seeds also checks the revealed hash matches the vault address
+        let seeds = vault_signer_seeds(&self.instruction_data.hash, &self.instruction_data.bump)
+            .map(Seed::from);
+        let signers = [Signer::from(&seeds)];
+
+        allocate_vault(self.accounts.vault, &signers)?;
+
+        self.accounts.vault.try_borrow_mut_data()?.copy_from_slice(
+            &VaultState::new(
+                self.instruction_data.hash,
+                self.instruction_data.bump,
+                *self.accounts.payer.key(),
+            )
+            .to_bytes(),
+        );
+
+        // Consume the commitment
+        credit(self.accounts.payer, self.accounts.commitment.lamports())?;
+        self.accounts.commitment.close().map_err(map_borrow_error)
+    }
+}
@@ -0,0 +1,238 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::find_program_address,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::{Allocate, Assign};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    error::VaultError,
+    message::SpendMessage,
+    state::VaultState,
+    vault::{
+        check_recipient, split_signature, vault_signer_seeds, RecoveredVault, Salt, Vault,
+        SIGNATURE_LEN,
+    },
+};
+
+/*
+    Spend and keep the change protected: pay `amount` to a recipient and move everything else into
+    a fresh vault for a new winternitz key, in one instruction. The signed message commits to the
+    amount, the recipient and the new key's hash, so the change can only land in the vault the
+    signer chose. The new vault is opened here, paid for out of the old vault's balance, and keeps
+    the old vault's opener. With a rent collector configured the old vault's rent goes to the
+    collector as in a split, and the new vault's rent comes out of the change.
+*/
+pub struct RotateVaultAccounts<'a> {
+    pub vault: &'a AccountInfo,     // vault being spent (mutable)
+    pub recipient: &'a AccountInfo, // receives amount (mutable)
+    pub new_vault: &'a AccountInfo, // unopened vault of the new key, receives the change (mutable)
+    #[cfg(feature = "rent-collector")]
+    pub collector: &'a AccountInfo, // Receives the old vault's rent-exempt reserve (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for RotateVaultAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        #[cfg(not(feature = "rent-collector"))]
+        let [vault, recipient, new_vault, _system_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        let [vault, recipient, new_vault, _system_program, collector] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(collector)?;
+
+        // A vault already spent (e.g. by an earlier rotation in the same block) is an empty system
+        // account
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
+
+        if !vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // Both credits would be wiped out when the vault is closed
+        if vault.key() == recipient.key() || vault.key() == new_vault.key() {
+            return Err(VaultError::AccountAliasing.into());
+        }
+
+        Ok(Self {
+            vault,
+            recipient,
+            new_vault,
+            #[cfg(feature = "rent-collector")]
+            collector,
+        })
+    }
+}
+
+pub struct RotateVaultInstructionData {
+    pub signature: WinternitzSignature, // signature over disc | amount | recipient | new_hash
+    pub bump: [u8; 1],                  // vault PDA derivation bump
+    pub amount: [u8; 8],                // lamports to pay the recipient
+    pub new_hash: [u8; 32],             // merklized pubkey of the new vault's key
+    pub new_bump: [u8; 1],              // canonical bump of the new vault
+    pub salt: Salt,                     // passphrase hash for the vault seed (all zero for none)
+}
+
+impl RotateVaultInstructionData {
+    // signature + bump + amount + new hash + new bump (+ salt under `passphrase-salt`)
+    pub const LEN: usize = SIGNATURE_LEN + 1 + 8 + 32 + 1 + Salt::LEN;
+}
+
+impl<'a> TryFrom<&'a [u8]> for RotateVaultInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (data, salt) = Salt::split_off(data)?;
+        let (signature, rest) = split_signature(data)?;
+        let (bump, rest) = rest.split_at(1);
+        let (amount, rest) = rest.split_at(8);
+        let (new_hash, new_bump) = rest.split_at(32);
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            amount: amount
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            new_hash: new_hash
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            new_bump: new_bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            salt,
+        })
+    }
+}
+
+pub struct RotateVault<'a> {
+    pub accounts: RotateVaultAccounts<'a>,
+    pub instruction_data: RotateVaultInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for RotateVault<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = RotateVaultAccounts::try_from(accounts)?;
+        let instruction_data = RotateVaultInstructionData::try_from(data)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> RotateVault<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &12;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_spend(
+            self.accounts.vault,
+            &self.instruction_data.bump,
+            &[self.accounts.recipient, self.accounts.new_vault],
+        )?;
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        if self.accounts.new_vault.is_owned_by(&crate::ID)
+            || self.accounts.new_vault.data_len() != 0
+        {
+            return Err(VaultError::VaultAlreadyExists.into());
+        }
+
+        // The change must at least cover the new vault's rent
+        let amount = u64::from_le_bytes(self.instruction_data.amount);
+        let change = vault
+            .spendable()?
+            .checked_sub(amount)
+            .ok_or(VaultError::InsufficientFunds)?;
+        if change.saturating_add(self.accounts.new_vault.lamports())
+            < Rent::get()?.minimum_balance(VaultState::LEN)
+        {
+            return Err(VaultError::InsufficientFunds.into());
+        }
+
+        check_recipient(self.accounts.recipient, amount)?;
+
+        let (_, canonical_bump) =
+            find_program_address(&[&self.instruction_data.new_hash], &crate::ID);
+        if self.instruction_data.new_bump != [canonical_bump] {
+            return Err(VaultError::NonCanonicalBump.into());
+        }
+
+        // The new hash takes the refund slot of a split message
+        let mut message = SpendMessage::new(*Self::DISCRIMINATOR);
+        message.push_recipient(&self.instruction_data.amount, self.accounts.recipient.key())?;
+        message.push_refund(&self.instruction_data.new_hash)?;
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            message.as_bytes(),
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        // Open the new vault in place, signing with its seeds (which fails unless the hash matches
+        // the address). Lamports already sent to the address are kept.
+        let seeds = vault_signer_seeds(
+            &self.instruction_data.new_hash,
+            &self.instruction_data.new_bump,
+        )
+        .map(Seed::from);
+        let signers = [Signer::from(&seeds)];
+
+        Allocate {
+            account: self.accounts.new_vault,
+            space: VaultState::LEN as u64,
+        }
+        .invoke_signed(&signers)?;
+
+        Assign {
+            account: self.accounts.new_vault,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signers)?;
+
+        // The key holder's change pays the new vault's rent, so no opener may cancel or reclaim it
+        self.accounts.new_vault.try_borrow_mut_data()?.copy_from_slice(
+            &VaultState::new(
+                self.instruction_data.new_hash,
+                self.instruction_data.new_bump,
+                VaultState::NO_OPENER,
+            )
+            .to_bytes(),
+        );
+
+        // Lamports move only after the CPIs, so the runtime sees consistent balances around them
+        vault.transfer(self.accounts.recipient, amount)?;
+
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        vault.close_to(self.accounts.new_vault)
+    }
+}
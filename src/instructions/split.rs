@@ -1,8 +1,16 @@
-use std::mem::MaybeUninit;
-
-use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
 use solana_winternitz::signature::WinternitzSignature;
 
+use crate::{
+    error::VaultError,
+    message::SpendMessage,
+    vault::{
+        check_recipient, check_refund, split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN,
+    },
+};
+
 /*
     unline traditonal cryptography, winternitz signature become vulnerable after a single use.
     this split instruction allows you to:
@@ -14,20 +22,42 @@ pub struct SplitVaultAccounts<'a> {
     pub vault: &'a AccountInfo, // source vault containing stored lamports (mutable)
     pub split: &'a AccountInfo, // recipient account for the spcified amount (mutable)
     pub refund: &'a AccountInfo, // Recipient account for remaining vault balance (mutable)
+    #[cfg(feature = "rent-collector")]
+    pub collector: &'a AccountInfo, // Receives the vault's rent-exempt reserve (mutable)
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for SplitVaultAccounts<'a> {
     type Error = ProgramError;
 
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        #[cfg(not(feature = "rent-collector"))]
         let [vault, split, refund] = accounts else {
             return Err(ProgramError::NotEnoughAccountKeys);
         };
+        #[cfg(feature = "rent-collector")]
+        let [vault, split, refund, collector] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(collector)?;
+
+        // A vault already spent (e.g. by an earlier split in the same block) is an empty system
+        // account
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
+
+        // The split credit would be wiped out when the vault is closed
+        if vault.key() == split.key() {
+            return Err(VaultError::AccountAliasing.into());
+        }
 
         Ok(Self {
             vault,
             split,
             refund,
+            #[cfg(feature = "rent-collector")]
+            collector,
         })
     }
 }
@@ -36,32 +66,37 @@ pub struct SplitVaultInstructionData {
     pub siganture: WinternitzSignature, // winterenitz signature proving ownership of the vault's keypair
     pub amount: [u8; 8],                // lamports to transfer to the split account
     pub bump: [u8; 1],                  // PDA derivation bump for optimization
+    pub salt: Salt,                     // passphrase hash for the vault seed (all zero for none)
+}
+
+impl SplitVaultInstructionData {
+    pub const BUMP_LEN: usize = 1;
+    pub const AMOUNT_LEN: usize = 8;
+    // signature + bump + amount (+ salt under `passphrase-salt`), with no trailing bytes (906 bytes
+    // including the discriminator without a salt)
+    pub const LEN: usize = SIGNATURE_LEN + Self::BUMP_LEN + Self::AMOUNT_LEN + Salt::LEN;
 }
 
 impl<'a> TryFrom<&'a [u8]> for SplitVaultInstructionData {
     type Error = ProgramError;
     fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-        if data.len() != core::mem::size_of::<SplitVaultInstructionData>() {
+        if data.len() != Self::LEN {
             return Err(ProgramError::InvalidInstructionData);
         };
 
-        let mut signature_array = MaybeUninit::<[u8; 896]>::uninit();
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                data[0..896].as_ptr(),
-                signature_array.as_mut_ptr() as *mut u8,
-                896,
-            );
-        }
+        let (data, salt) = Salt::split_off(data)?;
+        let (signature, rest) = split_signature(data)?;
+        let (bump, amount) = rest.split_at(Self::BUMP_LEN);
 
         Ok(Self {
-            siganture: WinternitzSignature::from(unsafe { signature_array.assume_init() }),
-            bump: data[896..897]
+            siganture: signature,
+            bump: bump
                 .try_into()
                 .map_err(|_| ProgramError::InvalidInstructionData)?,
-            amount: data[897..905]
+            amount: amount
                 .try_into()
                 .map_err(|_| ProgramError::InvalidInstructionData)?,
+            salt,
         })
     }
 }
@@ -93,49 +128,109 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SplitVault<'a> {
 
 impl<'a> SplitVault<'a> {
     pub const DISCRIMINATOR: &'a u8 = &1;
+    // [discriminator | amount | split | refund]
+    pub const MESSAGE_LEN: usize = SpendMessage::recipient_offset(1) + 32;
 
     /*
        The verification process follows these steps:
-       Message Assembly: A 72-byte message is constructed containing: Amount to split, the split account publickey and the refund account publickey
+       Message Assembly: A 73-byte message is constructed containing: the discriminator, Amount to split, the split account publickey and the refund account publickey
        Signature Verification: The Winternitz signature is used to recover the original public key hash, which is then compared against the vault's PDA derivation seeds.
        PDA Validation: A fast equivalence check ensures the recovered hash matches the vault's PDA, proving the signer owns the vault.
        Fund Distribution If validation succeeds: the specified amount is transferred to the split account, the remaining balance is transferred to the refund account and the vault acount is closed.
+       If the amount is the vault's entire balance, the refund account receives nothing: it is left untouched and, if it didn't exist yet, it isn't created.
+    */
+
+    /*
+        Validate the amount against the vault before any signature work or mutation, rather than
+        trusting it because it was signed. Returns the parsed vault and the amount.
     */
+    fn verify_split(&self) -> Result<(Vault<'a>, u64), ProgramError> {
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        let amount = u64::from_le_bytes(self.instruction_data.amount);
+        let remainder = vault
+            .spendable()?
+            .checked_sub(amount)
+            .ok_or(VaultError::InsufficientFunds)?;
+
+        self.accounts
+            .split
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // A split account that is also the refund account gets the remainder too, so its final
+        // balance isn't known here; leave that case to the runtime's rent check
+        if self.accounts.split.key() != self.accounts.refund.key() {
+            check_recipient(self.accounts.split, amount)?;
+            check_refund(self.accounts.refund, remainder)?;
+        }
+
+        Ok((vault, amount))
+    }
+
+    // The split message, prefixed with the discriminator for domain separation:
+    // [discriminator | amount | split | refund]. Clients must sign exactly these bytes.
+    pub fn message(
+        amount: &[u8; 8],
+        split: &Pubkey,
+        refund: &Pubkey,
+    ) -> Result<SpendMessage, VaultError> {
+        let mut message = SpendMessage::new(*Self::DISCRIMINATOR);
+        message.push_recipient(amount, split)?;
+        message.push_refund(refund)?;
+        Ok(message)
+    }
 
     pub fn process(&self) -> ProgramResult {
-        // assemble our split message
-        let mut message = [0u8; 72];
-        message[0..8].clone_from_slice(&self.instruction_data.amount);
-        message[8..40].clone_from_slice(self.accounts.split.key());
-        message[40..].clone_from_slice(self.accounts.refund.key());
-
-        // Recover pubkey from hash from the signature
-        let hash = self
-            .instruction_data
-            .siganture
-            .recover_pubkey(&message)
-            .merklize();
-
-        // Fast PDA equivalence check
-        if solana_nostd_sha256::hashv(&[
-            hash.as_ref(),
-            self.instruction_data.bump.as_ref(),
-            crate::ID.as_ref(),
-            b"ProgramDerivedAddress",
-        ])
-        .ne(self.accounts.vault.key())
-        {
-            return Err(ProgramError::MissingRequiredSignature);
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_spend(
+            self.accounts.vault,
+            &self.instruction_data.bump,
+            &[self.accounts.split, self.accounts.refund],
+        )?;
+
+        let (vault, amount) = self.verify_split()?;
+
+        let message = Self::message(
+            &self.instruction_data.amount,
+            self.accounts.split.key(),
+            self.accounts.refund.key(),
+        )?;
+
+        RecoveredVault::verify(
+            &self.instruction_data.siganture,
+            message.as_bytes(),
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        // Send split balance to Split account, any collected rent to the collector, and close the
+        // vault to the refund account, which gets what's left (nothing if amount is the whole
+        // balance: the refund account is then left untouched).
+        // Aliasing that creates or destroys lamports (e.g. refund == vault) fails the runtime's
+        // balance check and the whole instruction is rolled back.
+        #[cfg(feature = "verbose-logs")]
+        crate::logs::log_amount("split amount:", amount);
+
+        vault.transfer(self.accounts.split, amount)?;
+
+        #[cfg(feature = "anchor-events")]
+        crate::events::VaultSplit {
+            vault: *self.accounts.vault.key(),
+            split: *self.accounts.split.key(),
+            refund: *self.accounts.refund.key(),
+            amount,
         }
+        .emit();
 
-        // Close vault, send split balance to Split account, refund remainder to refund account
-        *self.accounts.split.try_borrow_mut_lamports()? +=
-            u64::from_le_bytes(self.instruction_data.amount);
-        *self.accounts.refund.try_borrow_mut_lamports()? += self
-            .accounts
-            .vault
-            .lamports()
-            .saturating_sub(u64::from_le_bytes(self.instruction_data.amount));
-        self.accounts.vault.close()
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        vault.close_to(self.accounts.refund)
     }
 }
@@ -0,0 +1,145 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    split::SplitVaultAccounts,
+    vault::{
+        check_recipient, check_refund, split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN,
+    },
+};
+
+/*
+    A split whose amount is a share of the vault's balance in basis points (1/100 of a percent)
+    instead of a lamport amount, for when the exact balance isn't known at signing time (e.g. more
+    deposits may still land). The share is taken from the balance at execution time, rounding
+    down, and the refund account gets the rest. Takes the same accounts as a split.
+*/
+pub struct SplitBpsInstructionData {
+    pub signature: WinternitzSignature,
+    pub bump: [u8; 1],
+    pub bps: [u8; 2], // share of the balance for the split account, at most 10,000 (little-endian)
+    pub salt: Salt,   // passphrase hash for the vault seed (all zero for none)
+}
+
+impl SplitBpsInstructionData {
+    pub const BUMP_LEN: usize = 1;
+    pub const BPS_LEN: usize = 2;
+    // signature + bump + bps (+ salt under `passphrase-salt`)
+    pub const LEN: usize = SIGNATURE_LEN + Self::BUMP_LEN + Self::BPS_LEN + Salt::LEN;
+}
+
+impl<'a> TryFrom<&'a [u8]> for SplitBpsInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (data, salt) = Salt::split_off(data)?;
+        let (signature, rest) = split_signature(data)?;
+        let (bump, bps) = rest.split_at(Self::BUMP_LEN);
+
+        let bps: [u8; 2] = bps
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        if u16::from_le_bytes(bps) > SplitBps::MAX_BPS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            bps,
+            salt,
+        })
+    }
+}
+
+pub struct SplitBps<'a> {
+    pub accounts: SplitVaultAccounts<'a>,
+    pub instruction_data: SplitBpsInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SplitBps<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = SplitVaultAccounts::try_from(accounts)?;
+        let instruction_data = SplitBpsInstructionData::try_from(data)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> SplitBps<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &14;
+
+    pub const MAX_BPS: u16 = 10_000;
+
+    // The signed message: [discriminator | bps | split | refund]
+    pub fn message(bps: &[u8; 2], split: &Pubkey, refund: &Pubkey) -> [u8; 67] {
+        let mut message = [0u8; 67];
+        message[0] = *Self::DISCRIMINATOR;
+        message[1..3].copy_from_slice(bps);
+        message[3..35].copy_from_slice(split);
+        message[35..].copy_from_slice(refund);
+        message
+    }
+
+    // `bps` of `balance`, rounded down. Can't overflow: the product fits in a u128 and the result
+    // is at most `balance`.
+    pub fn share(balance: u64, bps: u16) -> u64 {
+        (balance as u128 * bps as u128 / Self::MAX_BPS as u128) as u64
+    }
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        crate::strict::check_spend(
+            self.accounts.vault,
+            &self.instruction_data.bump,
+            &[self.accounts.split, self.accounts.refund],
+        )?;
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        // The collected rent is not part of the balance being shared
+        let balance = vault.spendable()?;
+        let amount = Self::share(balance, u16::from_le_bytes(self.instruction_data.bps));
+        if self.accounts.split.key() != self.accounts.refund.key() {
+            check_recipient(self.accounts.split, amount)?;
+            check_refund(self.accounts.refund, balance - amount)?;
+        }
+
+        let message = Self::message(
+            &self.instruction_data.bps,
+            self.accounts.split.key(),
+            self.accounts.refund.key(),
+        );
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            &message,
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        vault.transfer(self.accounts.split, amount)?;
+
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        vault.close_to(self.accounts.refund)
+    }
+}
@@ -0,0 +1,255 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    error::VaultError,
+    message::{entries_hash, SpendMessage},
+    vault::{
+        check_recipient, check_refund, split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN,
+    },
+};
+
+/*
+    Split a vault across many recipients while keeping the signed message a fixed 73 bytes:
+    [discriminator | total amount | recipients hash | refund]
+
+    The recipients hash is the SHA-256 of every (amount, recipient) pair in account order, so the
+    signature commits to each amount and destination without the message growing with them. The
+    recipients are passed as the trailing accounts and their amounts in the instruction data; the
+    program recomputes the hash from those, so any change to either fails signature verification.
+*/
+pub struct SplitManyAccounts<'a> {
+    pub vault: &'a AccountInfo,  // source vault containing stored lamports (mutable)
+    pub refund: &'a AccountInfo, // recipient of the remaining vault balance (mutable)
+    #[cfg(feature = "rent-collector")]
+    pub collector: &'a AccountInfo, // Receives the vault's rent-exempt reserve (mutable)
+    pub recipients: &'a [AccountInfo], // one account per amount, in the same order (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SplitManyAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        #[cfg(not(feature = "rent-collector"))]
+        let [vault, refund, recipients @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        let [vault, refund, collector, recipients @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(collector)?;
+
+        if recipients.is_empty() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
+
+        // Credits to the vault itself would be wiped out when it's closed
+        if recipients.iter().any(|recipient| recipient.key() == vault.key()) {
+            return Err(VaultError::AccountAliasing.into());
+        }
+
+        Ok(Self {
+            vault,
+            refund,
+            #[cfg(feature = "rent-collector")]
+            collector,
+            recipients,
+        })
+    }
+}
+
+pub struct SplitManyInstructionData<'a> {
+    pub signature: WinternitzSignature,
+    pub bump: [u8; 1],
+    pub amounts: &'a [u8], // one little-endian u64 per recipient
+    pub salt: Salt,        // passphrase hash for the vault seed (all zero for none)
+}
+
+impl SplitManyInstructionData<'_> {
+    pub const BUMP_LEN: usize = 1;
+    pub const AMOUNT_LEN: usize = 8;
+    // signature + bump + an amount for every recipient a spend message holds (+ salt)
+    pub const MAX_LEN: usize = SIGNATURE_LEN
+        + Self::BUMP_LEN
+        + SpendMessage::MAX_RECIPIENTS * Self::AMOUNT_LEN
+        + Salt::LEN;
+
+    pub fn amount(&self, index: usize) -> u64 {
+        let offset = index * Self::AMOUNT_LEN;
+        let mut amount = [0u8; 8];
+        amount.copy_from_slice(&self.amounts[offset..offset + Self::AMOUNT_LEN]);
+        u64::from_le_bytes(amount)
+    }
+
+    pub fn len(&self) -> usize {
+        self.amounts.len() / Self::AMOUNT_LEN
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amounts.is_empty()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for SplitManyInstructionData<'a> {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        // The salt (if any) follows the amounts
+        let (data, salt) = Salt::split_off(data)?;
+        if data.len() < SIGNATURE_LEN + Self::BUMP_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (signature, rest) = split_signature(data)?;
+        let (bump, amounts) = rest.split_at(Self::BUMP_LEN);
+
+        if amounts.len() % Self::AMOUNT_LEN != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            amounts,
+            salt,
+        })
+    }
+}
+
+pub struct SplitMany<'a> {
+    pub accounts: SplitManyAccounts<'a>,
+    pub instruction_data: SplitManyInstructionData<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SplitMany<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = SplitManyAccounts::try_from(accounts)?;
+        let instruction_data = SplitManyInstructionData::try_from(data)?;
+
+        // Every recipient needs exactly one amount
+        if instruction_data.len() != accounts.recipients.len() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> SplitMany<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &13;
+
+    // SHA-256 over [amount | recipient] for each recipient in order (see `entries_hash`)
+    pub fn recipients_hash<'b>(
+        amounts: &'b [u8],
+        recipients: impl IntoIterator<Item = &'b Pubkey>,
+    ) -> Result<[u8; 32], VaultError> {
+        entries_hash(amounts, SplitManyInstructionData::AMOUNT_LEN, recipients)
+    }
+
+    /*
+        The signed message: [discriminator | total amount | recipients hash | refund], i.e. a
+        spend message whose single "recipient" is the hash of the real ones
+    */
+    pub fn message(
+        total: u64,
+        recipients_hash: &[u8; 32],
+        refund: &Pubkey,
+    ) -> Result<SpendMessage, VaultError> {
+        let mut message = SpendMessage::new(*Self::DISCRIMINATOR);
+        message.push_recipient(&total.to_le_bytes(), recipients_hash)?;
+        message.push_refund(refund)?;
+        Ok(message)
+    }
+
+    // Check the amounts against the vault and each recipient; returns the vault and the total
+    fn verify_split(&self) -> Result<(Vault<'a>, u64), ProgramError> {
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        let mut total: u64 = 0;
+        for (index, recipient) in self.accounts.recipients.iter().enumerate() {
+            let amount = self.instruction_data.amount(index);
+            total = total
+                .checked_add(amount)
+                .ok_or(VaultError::InsufficientFunds)?;
+
+            // A recipient that is also the refund account gets the remainder too, so leave it to
+            // the runtime's rent check. A repeated recipient is checked against each amount alone.
+            if recipient.key() != self.accounts.refund.key() {
+                check_recipient(recipient, amount)?;
+            }
+        }
+
+        let remainder = vault
+            .spendable()?
+            .checked_sub(total)
+            .ok_or(VaultError::InsufficientFunds)?;
+
+        let refund = self.accounts.refund.key();
+        if self.accounts.recipients.iter().all(|recipient| recipient.key() != refund) {
+            check_refund(self.accounts.refund, remainder)?;
+        }
+
+        Ok((vault, total))
+    }
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        {
+            crate::strict::check_spend(
+                self.accounts.vault,
+                &self.instruction_data.bump,
+                &[self.accounts.refund],
+            )?;
+            for recipient in self.accounts.recipients {
+                crate::strict::check_writable(&[recipient])?;
+            }
+        }
+
+        if self.accounts.recipients.len() > SpendMessage::MAX_RECIPIENTS {
+            return Err(VaultError::MessageTooLong.into());
+        }
+
+        let (vault, total) = self.verify_split()?;
+
+        let recipients_hash = Self::recipients_hash(
+            self.instruction_data.amounts,
+            self.accounts.recipients.iter().map(|recipient| recipient.key()),
+        )?;
+        let message = Self::message(total, &recipients_hash, self.accounts.refund.key())?;
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            message.as_bytes(),
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        for (index, recipient) in self.accounts.recipients.iter().enumerate() {
+            vault.transfer(recipient, self.instruction_data.amount(index))?;
+        }
+
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        vault.close_to(self.accounts.refund)
+    }
+}
@@ -0,0 +1,223 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    error::VaultError,
+    message::SpendMessage,
+    state::Authorization,
+    vault::{split_signature, RecoveredVault, Salt, Vault, SIGNATURE_LEN},
+};
+
+/*
+    First half of a pre-authorized split ("pay button"): the key holder signs a split to a fixed
+    recipient and amount once, and the recipient claims it later with ExecuteAuthorization. The
+    signed expiry slot bounds the wait: a recipient who never shows up can't lock the funds, since
+    from that slot on anyone can execute the authorization back to the refund account.
+
+    A winternitz key is one-time, so the signature is consumed here: it's verified, the vault's
+    funds move into an authorization account recording the recipient, amount and refund, and the
+    vault is closed. Nothing about the key stays usable on-chain while the authorization waits.
+    With a rent collector configured the vault's rent goes to the collector here, as in a split,
+    and only the vault's funds move into the authorization.
+*/
+pub struct StoreAuthorizationAccounts<'a> {
+    pub payer: &'a AccountInfo,         // pays for the authorization account (signer, mutable)
+    pub vault: &'a AccountInfo,         // vault being spent (mutable)
+    pub authorization: &'a AccountInfo, // authorization PDA ["authorization", vault] to create (mutable)
+    #[cfg(feature = "rent-collector")]
+    pub collector: &'a AccountInfo, // Receives the vault's rent-exempt reserve (mutable)
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for StoreAuthorizationAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        #[cfg(not(feature = "rent-collector"))]
+        let [payer, vault, authorization, _system_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        let [payer, vault, authorization, _system_program, collector] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        #[cfg(feature = "rent-collector")]
+        crate::rent_collector::check_collector(collector)?;
+
+        // A vault already spent (e.g. by an earlier split in the same block) is an empty system
+        // account
+        if vault.lamports() == 0 {
+            return Err(VaultError::VaultAlreadyClosed.into());
+        }
+
+        if !vault.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self {
+            payer,
+            vault,
+            authorization,
+            #[cfg(feature = "rent-collector")]
+            collector,
+        })
+    }
+}
+
+pub struct StoreAuthorizationInstructionData {
+    pub signature: WinternitzSignature, // signs disc | amount | recipient | refund | expiry
+    pub bump: [u8; 1],                  // vault PDA derivation bump
+    pub amount: [u8; 8],                // lamports the recipient may claim
+    pub recipient: [u8; 32],            // account allowed to execute, receives amount
+    pub refund: [u8; 32],               // receives the rest of the vault's funds
+    pub expiry_slot: [u8; 8],           // slot from which the recipient can no longer claim
+    pub authorization_bump: [u8; 1],    // authorization PDA derivation bump
+    pub salt: Salt,                     // passphrase hash for the vault seed (all zero for none)
+}
+
+impl StoreAuthorizationInstructionData {
+    // signature + bump + amount + recipient + refund + expiry slot + authorization bump (+ salt
+    // under `passphrase-salt`)
+    pub const LEN: usize = SIGNATURE_LEN + 1 + 8 + 32 + 32 + 8 + 1 + Salt::LEN;
+}
+
+impl<'a> TryFrom<&'a [u8]> for StoreAuthorizationInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (data, salt) = Salt::split_off(data)?;
+        let (signature, rest) = split_signature(data)?;
+        let (bump, rest) = rest.split_at(1);
+        let (amount, rest) = rest.split_at(8);
+        let (recipient, rest) = rest.split_at(32);
+        let (refund, rest) = rest.split_at(32);
+        let (expiry_slot, authorization_bump) = rest.split_at(8);
+
+        Ok(Self {
+            signature,
+            bump: bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            amount: amount
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            recipient: recipient
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            refund: refund
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            expiry_slot: expiry_slot
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            authorization_bump: authorization_bump
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            salt,
+        })
+    }
+}
+
+pub struct StoreAuthorization<'a> {
+    pub accounts: StoreAuthorizationAccounts<'a>,
+    pub instruction_data: StoreAuthorizationInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for StoreAuthorization<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = StoreAuthorizationAccounts::try_from(accounts)?;
+        let instruction_data = StoreAuthorizationInstructionData::try_from(data)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> StoreAuthorization<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &10;
+
+    pub fn process(&self) -> ProgramResult {
+        #[cfg(feature = "strict-validation")]
+        {
+            crate::strict::check_signer(self.accounts.payer)?;
+            crate::strict::check_spend(
+                self.accounts.vault,
+                &self.instruction_data.bump,
+                &[self.accounts.authorization],
+            )?;
+        }
+
+        let vault = Vault::try_from(self.accounts.vault)?;
+
+        let amount = u64::from_le_bytes(self.instruction_data.amount);
+        if amount > vault.spendable()? {
+            return Err(VaultError::InsufficientFunds.into());
+        }
+
+        // A split message under this instruction's discriminator, followed by the expiry slot
+        let mut message = SpendMessage::new(*Self::DISCRIMINATOR);
+        message.push_recipient(
+            &self.instruction_data.amount,
+            &self.instruction_data.recipient,
+        )?;
+        message.push_refund(&self.instruction_data.refund)?;
+        message.push_suffix(&self.instruction_data.expiry_slot)?;
+
+        RecoveredVault::verify(
+            &self.instruction_data.signature,
+            message.as_bytes(),
+            &self.instruction_data.bump,
+            &self.instruction_data.salt,
+            self.accounts.vault,
+        )?;
+
+        let seeds = [
+            Seed::from(Authorization::SEED),
+            Seed::from(self.accounts.vault.key()),
+            Seed::from(&self.instruction_data.authorization_bump),
+        ];
+
+        CreateAccount {
+            from: self.accounts.payer,
+            to: self.accounts.authorization,
+            lamports: Rent::get()?.minimum_balance(Authorization::LEN),
+            space: Authorization::LEN as u64,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        self.accounts.authorization.try_borrow_mut_data()?.copy_from_slice(
+            &Authorization::new(
+                self.instruction_data.recipient,
+                self.instruction_data.refund,
+                *self.accounts.payer.key(),
+                amount,
+                u64::from_le_bytes(self.instruction_data.expiry_slot),
+            )
+            .to_bytes(),
+        );
+
+        #[cfg(feature = "rent-collector")]
+        vault.transfer(
+            self.accounts.collector,
+            crate::rent_collector::rent_share(self.accounts.vault)?,
+        )?;
+
+        // Move the rest of the vault's funds into the authorization and close the vault
+        vault.close_to(self.accounts.authorization)
+    }
+}
@@ -1,4 +1,19 @@
+#[cfg(feature = "client")]
+pub mod client;
+pub mod compute;
+pub mod error;
+#[cfg(feature = "anchor-events")]
+pub mod events;
 pub mod instructions;
+#[cfg(feature = "verbose-logs")]
+pub mod logs;
+pub mod message;
+#[cfg(feature = "rent-collector")]
+pub mod rent_collector;
+pub mod state;
+#[cfg(feature = "strict-validation")]
+pub mod strict;
+pub mod vault;
 use pinocchio::{
     account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
 };
@@ -8,21 +23,117 @@ pub use instructions::*;
 #[cfg(not(feature = "no-entrypoint"))]
 use pinocchio::entrypoint;
 
-use crate::instructions::{close::CloseVault, open::OpenVault, split::SplitVault};
+use crate::error::VaultError;
+use crate::instructions::{
+    cancel_unfunded::CancelUnfunded,
+    close::CloseVault,
+    close_proportional::{CloseProportional, CloseProportionalInstructionData},
+    close_to_two::CloseToTwo,
+    commit_open::CommitOpen,
+    execute_authorization::ExecuteAuthorization,
+    open::OpenVault,
+    prove::ProveOwnership,
+    reclaim::ReclaimRent,
+    reveal_open::RevealOpen,
+    rotate::{RotateVault, RotateVaultInstructionData},
+    split::SplitVault,
+    split_bps::SplitBps,
+    split_many::{SplitMany, SplitManyInstructionData},
+    store_authorization::{StoreAuthorization, StoreAuthorizationInstructionData},
+};
+
+#[cfg(feature = "diagnose")]
+use crate::instructions::diagnose::Diagnose;
 
 #[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
 
-pub const ID: Pubkey = [
+/*
+    Program ID selection. Local builds (tests, localnet) use LOCALNET_ID. Deployments pick their ID
+    at build time with the `devnet` or `mainnet` feature, which read the base58 program ID from
+    QUANTUM_VAULT_DEVNET_ID / QUANTUM_VAULT_MAINNET_ID, e.g.
+    QUANTUM_VAULT_DEVNET_ID=<pubkey> cargo build-sbf --features devnet
+*/
+#[cfg(all(feature = "devnet", feature = "mainnet"))]
+compile_error!("features `devnet` and `mainnet` are mutually exclusive");
+
+pub const LOCALNET_ID: Pubkey = [
     0x0f, 0x1e, 0x6b, 0x14, 0x21, 0xc0, 0x4a, 0x07, 0x04, 0x31, 0x26, 0x5c, 0x19, 0xc5, 0xbb, 0xee,
     0x19, 0x92, 0xba, 0xe8, 0xaf, 0xd1, 0xcd, 0x07, 0x8e, 0xf8, 0xaf, 0x70, 0x47, 0xdc, 0x11, 0xf7,
 ];
 
+#[cfg(feature = "devnet")]
+pub const ID: Pubkey = pinocchio_pubkey::from_str(env!("QUANTUM_VAULT_DEVNET_ID"));
+
+#[cfg(feature = "mainnet")]
+pub const ID: Pubkey = pinocchio_pubkey::from_str(env!("QUANTUM_VAULT_MAINNET_ID"));
+
+#[cfg(not(any(feature = "devnet", feature = "mainnet")))]
+pub const ID: Pubkey = LOCALNET_ID;
+
+/*
+    Recommended compute unit limits, so clients can request a budget instead of a blanket 1.4M.
+    Open is a canonical bump search and a single CreateAccount CPI. Split and close are dominated
+    by winternitz recovery, which hashes each of the 32 chains up to 255 times depending on the
+    signed digest: ~4,000 hashes (~550k CU) on average and 8,160 in the very worst case. The
+    limits leave headroom for digests far beyond the average.
+*/
+pub const OPEN_CU: u32 = 20_000;
+pub const SPLIT_CU: u32 = 1_000_000;
+pub const CLOSE_CU: u32 = 1_000_000;
+
+/*
+    The high nibble of every discriminator carries the ABI version, leaving the low nibble for up
+    to 16 instructions. Instructions tagged with any other version are rejected instead of being
+    misread, so clients can detect that they're talking to an incompatible program.
+
+    Version 0 is full: all 16 slots are assigned (6, Diagnose, is only dispatched under
+    `diagnose`, but stays reserved so its tag never means anything else). Another instruction
+    needs a new ABI version, which also re-tags every signed message and so invalidates
+    signatures made for this one, or an extended discriminator: a version whose slot is followed
+    by a second byte selecting the instruction.
+*/
+pub const ABI_VERSION: u8 = 0;
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/*
+    The longest instruction data any instruction accepts, discriminator included: every other
+    signed instruction is shorter than these. Anything longer is rejected before dispatch instead
+    of being parsed and hashed. Under `diagnose`, a diagnosis of the longest signed message (a
+    stored authorization's) is exactly as long as the StoreAuthorization instruction it's from.
+*/
+pub const MAX_INSTRUCTION_DATA_LEN: usize = 1 + max(
+    max(StoreAuthorizationInstructionData::LEN, RotateVaultInstructionData::LEN),
+    max(SplitManyInstructionData::MAX_LEN, CloseProportionalInstructionData::MAX_LEN),
+);
+
 pub fn process_instruction(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
+    // PDAs are derived from ID, so a deployment under any other address can't own its vaults
+    if program_id.ne(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if instruction_data.len() > MAX_INSTRUCTION_DATA_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if let Some(discriminator) = instruction_data.first() {
+        if discriminator >> 4 != ABI_VERSION {
+            return Err(VaultError::UnsupportedAbiVersion.into());
+        }
+    }
+
     match instruction_data.split_first() {
         Some((OpenVault::DISCRIMINATOR, data)) => OpenVault::try_from((data, accounts))?.process(),
         Some((SplitVault::DISCRIMINATOR, data)) => {
@@ -31,6 +142,40 @@ pub fn process_instruction(
         Some((CloseVault::DISCRIMINATOR, data)) => {
             CloseVault::try_from((data, accounts))?.process()
         }
+        Some((CloseToTwo::DISCRIMINATOR, data)) => {
+            CloseToTwo::try_from((data, accounts))?.process()
+        }
+        Some((ProveOwnership::DISCRIMINATOR, data)) => {
+            ProveOwnership::try_from((data, accounts))?.process()
+        }
+        Some((ReclaimRent::DISCRIMINATOR, data)) => {
+            ReclaimRent::try_from((data, accounts))?.process()
+        }
+        #[cfg(feature = "diagnose")]
+        Some((Diagnose::DISCRIMINATOR, data)) => Diagnose::try_from((data, accounts))?.process(),
+        Some((CommitOpen::DISCRIMINATOR, data)) => {
+            CommitOpen::try_from((data, accounts))?.process()
+        }
+        Some((RevealOpen::DISCRIMINATOR, data)) => {
+            RevealOpen::try_from((data, accounts))?.process()
+        }
+        Some((CancelUnfunded::DISCRIMINATOR, data)) => {
+            CancelUnfunded::try_from((data, accounts))?.process()
+        }
+        Some((StoreAuthorization::DISCRIMINATOR, data)) => {
+            StoreAuthorization::try_from((data, accounts))?.process()
+        }
+        Some((ExecuteAuthorization::DISCRIMINATOR, data)) => {
+            ExecuteAuthorization::try_from((data, accounts))?.process()
+        }
+        Some((RotateVault::DISCRIMINATOR, data)) => {
+            RotateVault::try_from((data, accounts))?.process()
+        }
+        Some((SplitMany::DISCRIMINATOR, data)) => SplitMany::try_from((data, accounts))?.process(),
+        Some((SplitBps::DISCRIMINATOR, data)) => SplitBps::try_from((data, accounts))?.process(),
+        Some((CloseProportional::DISCRIMINATOR, data)) => {
+            CloseProportional::try_from((data, accounts))?.process()
+        }
         _ => Err(ProgramError::InvalidInstructionData),
     }
 }
@@ -0,0 +1,78 @@
+use pinocchio::{msg, pubkey::Pubkey};
+
+/*
+    Logged when a spend fails the PDA check: the hash recovered from the signature and the vault
+    it was expected to derive. A hash belonging to a different key of yours points to the wrong
+    key; a hash you don't recognize at all points to a message built differently from the one
+    that was signed.
+*/
+pub fn log_pda_mismatch(recovered_hash: &[u8; 32], vault: &Pubkey) {
+    msg!("PDA check failed, recovered hash:");
+    pinocchio::pubkey::log(recovered_hash);
+    msg!("expected vault:");
+    pinocchio::pubkey::log(vault);
+}
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+// Longest formatted amount: 20 lamport digits, 11 SOL digits plus 9 decimals, and the text
+pub const AMOUNT_MAX_LEN: usize = 64;
+
+// Logs an amount moved by a spend, in lamports and SOL
+pub fn log_amount(label: &str, lamports: u64) {
+    let mut buf = [0u8; AMOUNT_MAX_LEN];
+    msg!(label);
+    msg!(format_amount(lamports, &mut buf));
+}
+
+/*
+    Formats `lamports` as "<lamports> lamports (<sol> SOL)", e.g. "2000000000 lamports (2.0 SOL)",
+    with integer arithmetic only so no float formatting gets linked in. The SOL amount is exact:
+    trailing zeros of the 9 decimals are dropped, keeping at least one.
+*/
+pub fn format_amount(lamports: u64, buf: &mut [u8; AMOUNT_MAX_LEN]) -> &str {
+    let mut len = write_u64(buf, lamports);
+    len += write_str(&mut buf[len..], " lamports (");
+    len += write_u64(&mut buf[len..], lamports / LAMPORTS_PER_SOL);
+    len += write_str(&mut buf[len..], ".");
+
+    let mut decimals = [b'0'; 9];
+    let mut fraction = lamports % LAMPORTS_PER_SOL;
+    for digit in decimals.iter_mut().rev() {
+        *digit = b'0' + (fraction % 10) as u8;
+        fraction /= 10;
+    }
+    let mut end = decimals.len();
+    while end > 1 && decimals[end - 1] == b'0' {
+        end -= 1;
+    }
+    buf[len..len + end].copy_from_slice(&decimals[..end]);
+    len += end;
+
+    len += write_str(&mut buf[len..], " SOL)");
+
+    // Only ASCII digits and text were written
+    core::str::from_utf8(&buf[..len]).unwrap_or_default()
+}
+
+fn write_u64(buf: &mut [u8], mut value: u64) -> usize {
+    let mut digits = [0u8; 20];
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+
+    let len = digits.len() - start;
+    buf[..len].copy_from_slice(&digits[start..]);
+    len
+}
+
+fn write_str(buf: &mut [u8], text: &str) -> usize {
+    buf[..text.len()].copy_from_slice(text.as_bytes());
+    text.len()
+}
@@ -0,0 +1,118 @@
+use pinocchio::pubkey::Pubkey;
+
+use crate::error::VaultError;
+
+/*
+    Domain tags. Every message a vault key signs starts with the discriminator of the instruction
+    it authorizes, so a signature is only valid for that instruction:
+
+    1  split          [1 | amount | split | refund]
+    2  close          [2 | refund]
+    3  close to two   [3 | funds | rent refund]
+    4  prove          [4 | challenge]
+    10 store auth.    [10 | amount | recipient | refund | expiry slot]
+    12 rotate         [12 | amount | recipient | new vault hash]
+    13 split many     [13 | total | recipients hash | refund]
+    14 split bps      [14 | bps | split | refund]
+    15 close prop.    [15 | shares hash]
+
+    The discriminator's high nibble is the ABI version (see ABI_VERSION), so a signature made for
+    one ABI version can't be replayed against another either. New signed instructions must use
+    their own discriminator as the tag, never a shared or implicit one.
+*/
+
+/*
+    Builder for spend messages paying several recipients:
+    [discriminator | (amount, recipient) * n | refund]
+
+    Offsets are derived from the recipient count instead of being hardcoded, and every write is
+    bounds checked against MAX_LEN, so the buffer lives on the stack. There's no need to hash the
+    message down to a fixed size first: winternitz signs (and recovers from) the SHA-256 digest of
+    whatever message it's given, so any length up to MAX_LEN works.
+*/
+pub struct SpendMessage {
+    data: [u8; Self::MAX_LEN],
+    len: usize,
+}
+
+impl SpendMessage {
+    pub const MAX_RECIPIENTS: usize = 8;
+    pub const RECIPIENT_LEN: usize = 8 + 32; // amount (u64, little-endian) + pubkey
+    pub const MAX_LEN: usize = 1 + Self::MAX_RECIPIENTS * Self::RECIPIENT_LEN + 32;
+
+    pub fn new(discriminator: u8) -> Self {
+        let mut data = [0u8; Self::MAX_LEN];
+        data[0] = discriminator;
+        Self { data, len: 1 }
+    }
+
+    // Offset of the i-th recipient's amount; its pubkey follows 8 bytes later
+    pub const fn recipient_offset(index: usize) -> usize {
+        1 + index * Self::RECIPIENT_LEN
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> Result<(), VaultError> {
+        let end = self
+            .len
+            .checked_add(bytes.len())
+            .filter(|end| *end <= Self::MAX_LEN)
+            .ok_or(VaultError::MessageTooLong)?;
+        self.data[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    // Recipients must all be pushed before the refund, which always has room left for it
+    pub fn push_recipient(
+        &mut self,
+        amount: &[u8; 8],
+        recipient: &Pubkey,
+    ) -> Result<(), VaultError> {
+        if self.len + Self::RECIPIENT_LEN + 32 > Self::MAX_LEN {
+            return Err(VaultError::MessageTooLong);
+        }
+        self.push(amount)?;
+        self.push(recipient)
+    }
+
+    pub fn push_refund(&mut self, refund: &Pubkey) -> Result<(), VaultError> {
+        self.push(refund)
+    }
+
+    // Fields signed after the refund, e.g. a stored authorization's expiry slot
+    pub fn push_suffix(&mut self, bytes: &[u8]) -> Result<(), VaultError> {
+        self.push(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+// Bounds the input of `entries_hash` so it can be assembled on the stack
+pub const MAX_HASHED_ENTRIES: usize = 16;
+
+/*
+    SHA-256 over [value | key] for each key in order, so a fixed-size message can commit to a value
+    per account: split many's amounts and close proportional's shares. `values` holds one
+    `value_len`-byte value per key; extra values or keys past the shorter of the two are ignored.
+*/
+pub fn entries_hash<'a>(
+    values: &'a [u8],
+    value_len: usize,
+    keys: impl IntoIterator<Item = &'a Pubkey>,
+) -> Result<[u8; 32], VaultError> {
+    let mut parts: [&[u8]; 2 * MAX_HASHED_ENTRIES] = [&[]; 2 * MAX_HASHED_ENTRIES];
+    let mut len = 0;
+
+    for (value, key) in values.chunks_exact(value_len).zip(keys) {
+        if len == parts.len() {
+            return Err(VaultError::MessageTooLong);
+        }
+        parts[len] = value;
+        parts[len + 1] = key;
+        len += 2;
+    }
+
+    Ok(solana_nostd_sha256::hashv(&parts[..len]))
+}
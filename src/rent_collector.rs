@@ -0,0 +1,37 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvars::{rent::Rent, Sysvar},
+};
+
+use crate::error::VaultError;
+
+/*
+    Built with the `rent-collector` feature, every spend that closes a vault sends the rent-exempt
+    part of the vault to a fixed collector (e.g. a service that subsidizes vault rent) while the
+    deposited funds go where the signature says. Routing rent is protocol policy, so it isn't part
+    of the signed message; the collector is passed as the last account and must match
+    RENT_COLLECTOR.
+*/
+pub const RENT_COLLECTOR: Pubkey =
+    pinocchio_pubkey::from_str(env!("QUANTUM_VAULT_RENT_COLLECTOR"));
+
+pub fn check_collector(collector: &AccountInfo) -> Result<(), ProgramError> {
+    if collector.key().ne(&RENT_COLLECTOR) {
+        return Err(VaultError::InvalidRentCollector.into());
+    }
+
+    if !collector.is_writable() {
+        return Err(ProgramError::Immutable);
+    }
+
+    Ok(())
+}
+
+// Part of the vault's balance owed to the collector: its rent, or everything if it holds less
+pub fn rent_share(vault: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(Rent::get()?
+        .minimum_balance(vault.data_len())
+        .min(vault.lamports()))
+}
@@ -0,0 +1,292 @@
+use crate::error::VaultError;
+
+/*
+    Data stored in every quantum vault account. The PDA seeds are kept in the account so that
+    indexers and explorers can decode a vault without knowing the winternitz pubkey up front.
+
+    The version byte lets the layout evolve: readers dispatch on it and reject versions they
+    don't know, instead of misreading the bytes. Bump VERSION whenever the layout changes.
+
+    Every field has a fixed size and offset (no variable-length data), so clients fetching many
+    vaults at once (getMultipleAccounts, getProgramAccounts memcmp filters) can read any field with
+    the *_OFFSET constants alone.
+
+    Layout (version 1, 68 bytes):
+    0       discriminator
+    1       version
+    2..34   hash (merklized winternitz pubkey, first PDA seed)
+    34      bump (second PDA seed)
+    35..67  opener (account that paid the vault's rent, receives it back on reclaim; all zero for
+            a vault whose rent the key holder paid, e.g. one opened by RotateVault)
+    67      burned (1 once the winternitz key has signed something without closing the vault)
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultState {
+    pub discriminator: u8,
+    pub version: u8,
+    pub hash: [u8; 32],
+    pub bump: [u8; 1],
+    pub opener: [u8; 32],
+    pub burned: bool,
+}
+
+impl VaultState {
+    pub const DISCRIMINATOR: u8 = 1;
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 1 + 1 + 32 + 1 + 32 + 1;
+    pub const VERSION_OFFSET: usize = 1;
+    pub const HASH_OFFSET: usize = 2;
+    pub const BUMP_OFFSET: usize = 34;
+    pub const OPENER_OFFSET: usize = 35;
+    pub const BURNED_OFFSET: usize = 67;
+    // Opener of a vault nobody else paid the rent of, so nobody can take it back
+    pub const NO_OPENER: [u8; 32] = [0; 32];
+
+    pub fn new(hash: [u8; 32], bump: [u8; 1], opener: [u8; 32]) -> Self {
+        Self {
+            discriminator: Self::DISCRIMINATOR,
+            version: Self::VERSION,
+            hash,
+            bump,
+            opener,
+            burned: false,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[0] = self.discriminator;
+        data[Self::VERSION_OFFSET] = self.version;
+        data[Self::HASH_OFFSET..Self::BUMP_OFFSET].copy_from_slice(&self.hash);
+        data[Self::BUMP_OFFSET..Self::OPENER_OFFSET].copy_from_slice(&self.bump);
+        data[Self::OPENER_OFFSET..Self::BURNED_OFFSET].copy_from_slice(&self.opener);
+        data[Self::BURNED_OFFSET] = self.burned as u8;
+        data
+    }
+
+    fn try_from_v1(data: &[u8]) -> Result<Self, VaultError> {
+        if data.len() != Self::LEN {
+            return Err(VaultError::InvalidVaultState);
+        }
+
+        let burned = match data[Self::BURNED_OFFSET] {
+            0 => false,
+            1 => true,
+            _ => return Err(VaultError::InvalidVaultState),
+        };
+
+        Ok(Self {
+            discriminator: data[0],
+            version: data[Self::VERSION_OFFSET],
+            hash: data[Self::HASH_OFFSET..Self::BUMP_OFFSET]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            bump: data[Self::BUMP_OFFSET..Self::OPENER_OFFSET]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            opener: data[Self::OPENER_OFFSET..Self::BURNED_OFFSET]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            burned,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for VaultState {
+    type Error = VaultError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        let [discriminator, version, ..] = data else {
+            return Err(VaultError::InvalidVaultState);
+        };
+
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(VaultError::InvalidDiscriminator);
+        }
+
+        match *version {
+            1 => Self::try_from_v1(data),
+            _ => Err(VaultError::UnsupportedStateVersion),
+        }
+    }
+}
+
+/*
+    Commitment to open a vault, created by CommitOpen at PDA ["commitment", vault] and consumed by
+    RevealOpen. Only the committer can reveal (open) the vault it commits to. A commitment that
+    was never revealed can be cancelled by anyone once it's EXPIRY_SLOTS old, its rent going to
+    the vault address, so squatting on a vault address only holds it up for that long and costs
+    the squatter the rent each time.
+
+    Layout (73 bytes):
+    0       discriminator
+    1..33   committer
+    33..65  vault
+    65..73  slot the commitment was made in (u64, little endian)
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Commitment {
+    pub discriminator: u8,
+    pub committer: [u8; 32],
+    pub vault: [u8; 32],
+    pub slot: u64,
+}
+
+impl Commitment {
+    pub const DISCRIMINATOR: u8 = 2;
+    pub const LEN: usize = 1 + 32 + 32 + 8;
+    pub const SEED: &'static [u8] = b"commitment";
+    // About ten minutes of slots: plenty to land the reveal after the commit
+    pub const EXPIRY_SLOTS: u64 = 1_500;
+
+    pub fn new(committer: [u8; 32], vault: [u8; 32], slot: u64) -> Self {
+        Self {
+            discriminator: Self::DISCRIMINATOR,
+            committer,
+            vault,
+            slot,
+        }
+    }
+
+    // From this slot on anyone can cancel the commitment, and nobody before
+    pub fn is_expired(&self, slot: u64) -> bool {
+        slot >= self.slot.saturating_add(Self::EXPIRY_SLOTS)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[0] = self.discriminator;
+        data[1..33].copy_from_slice(&self.committer);
+        data[33..65].copy_from_slice(&self.vault);
+        data[65..73].copy_from_slice(&self.slot.to_le_bytes());
+        data
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Commitment {
+    type Error = VaultError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(VaultError::InvalidVaultState);
+        }
+
+        if data[0] != Self::DISCRIMINATOR {
+            return Err(VaultError::InvalidDiscriminator);
+        }
+
+        Ok(Self {
+            discriminator: data[0],
+            committer: data[1..33]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            vault: data[33..65]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            slot: u64::from_le_bytes(
+                data[65..73]
+                    .try_into()
+                    .map_err(|_| VaultError::InvalidVaultState)?,
+            ),
+        })
+    }
+}
+
+/*
+    Pre-authorized split, created by StoreAuthorization at PDA ["authorization", vault] and
+    consumed by ExecuteAuthorization. It holds the vault's funds until the recipient claims them,
+    or until its expiry slot, after which anyone can return the funds to the refund account.
+
+    Layout (113 bytes):
+    0        discriminator
+    1..33    recipient (receives amount, must sign the execution)
+    33..65   refund (receives the rest of the vault's funds, or all of them once expired)
+    65..97   payer (paid the authorization's rent, receives it back)
+    97..105  amount (u64, little endian)
+    105..113 expiry slot (u64, little endian; u64::MAX never expires)
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Authorization {
+    pub discriminator: u8,
+    pub recipient: [u8; 32],
+    pub refund: [u8; 32],
+    pub payer: [u8; 32],
+    pub amount: u64,
+    pub expiry_slot: u64,
+}
+
+impl Authorization {
+    pub const DISCRIMINATOR: u8 = 3;
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 8 + 8;
+    pub const SEED: &'static [u8] = b"authorization";
+
+    pub fn new(
+        recipient: [u8; 32],
+        refund: [u8; 32],
+        payer: [u8; 32],
+        amount: u64,
+        expiry_slot: u64,
+    ) -> Self {
+        Self {
+            discriminator: Self::DISCRIMINATOR,
+            recipient,
+            refund,
+            payer,
+            amount,
+            expiry_slot,
+        }
+    }
+
+    // From this slot on the recipient can no longer claim the amount
+    pub fn is_expired(&self, slot: u64) -> bool {
+        slot >= self.expiry_slot
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[0] = self.discriminator;
+        data[1..33].copy_from_slice(&self.recipient);
+        data[33..65].copy_from_slice(&self.refund);
+        data[65..97].copy_from_slice(&self.payer);
+        data[97..105].copy_from_slice(&self.amount.to_le_bytes());
+        data[105..113].copy_from_slice(&self.expiry_slot.to_le_bytes());
+        data
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Authorization {
+    type Error = VaultError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.len() != Self::LEN {
+            return Err(VaultError::InvalidVaultState);
+        }
+
+        if data[0] != Self::DISCRIMINATOR {
+            return Err(VaultError::InvalidDiscriminator);
+        }
+
+        Ok(Self {
+            discriminator: data[0],
+            recipient: data[1..33]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            refund: data[33..65]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            payer: data[65..97]
+                .try_into()
+                .map_err(|_| VaultError::InvalidVaultState)?,
+            amount: u64::from_le_bytes(
+                data[97..105]
+                    .try_into()
+                    .map_err(|_| VaultError::InvalidVaultState)?,
+            ),
+            expiry_slot: u64::from_le_bytes(
+                data[105..113]
+                    .try_into()
+                    .map_err(|_| VaultError::InvalidVaultState)?,
+            ),
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::find_program_address,
+    ProgramResult,
+};
+
+use crate::{error::VaultError, state::VaultState};
+
+/*
+    Defensive checks compiled in with the `strict-validation` feature. Without it, instructions
+    only check what their own logic needs and leave the rest to the runtime (e.g. a read-only
+    refund fails the runtime's write check, a vault passed as its own refund fails the balance
+    check). With it, every account is checked up front and rejected with a specific error, at the
+    cost of extra compute (a canonical bump search on every spend).
+*/
+
+pub fn check_writable(accounts: &[&AccountInfo]) -> ProgramResult {
+    if accounts.iter().any(|account| !account.is_writable()) {
+        return Err(ProgramError::Immutable);
+    }
+
+    Ok(())
+}
+
+pub fn check_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    check_writable(&[account])
+}
+
+/*
+    Checks for an instruction spending a vault with its winternitz key: the vault is an initialized
+    vault of this program at its canonical PDA, and it and every other account are writable and
+    distinct from it.
+*/
+pub fn check_spend(vault: &AccountInfo, bump: &[u8; 1], others: &[&AccountInfo]) -> ProgramResult {
+    if !vault.is_owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    check_writable(&[vault])?;
+    check_writable(others)?;
+
+    if others.iter().any(|account| account.key() == vault.key()) {
+        return Err(VaultError::AccountAliasing.into());
+    }
+
+    let state = VaultState::try_from(&vault.try_borrow_data()?[..])?;
+    if find_program_address(&[&state.hash], &crate::ID).1 != bump[0] {
+        return Err(VaultError::NonCanonicalBump.into());
+    }
+
+    Ok(())
+}
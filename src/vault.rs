@@ -0,0 +1,283 @@
+use std::mem::MaybeUninit;
+
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::Seed,
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use solana_winternitz::signature::WinternitzSignature;
+
+use crate::{
+    error::{map_borrow_error, VaultError},
+    state::VaultState,
+};
+
+/*
+    A vault account that has been checked to belong to this program and whose state has been
+    parsed, with the lamport moves spend instructions need. Each move drops its borrows before
+    returning, so aliased accounts never trip a borrow error; aliasing that creates or destroys
+    lamports (e.g. closing the vault to itself) fails the runtime's balance check instead.
+*/
+pub struct Vault<'a> {
+    pub account: &'a AccountInfo,
+    pub state: VaultState,
+}
+
+impl<'a> TryFrom<&'a AccountInfo> for Vault<'a> {
+    type Error = ProgramError;
+
+    fn try_from(account: &'a AccountInfo) -> Result<Self, Self::Error> {
+        if !account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // An account of any other size was spoofed or laid out by a different program version
+        if account.data_len() != VaultState::LEN {
+            return Err(VaultError::InvalidVaultState.into());
+        }
+
+        // Refuse vaults whose state layout this program doesn't understand
+        let state = VaultState::try_from(&account.try_borrow_data()?[..])?;
+
+        Ok(Self { account, state })
+    }
+}
+
+impl<'a> Vault<'a> {
+    pub fn balance(&self) -> u64 {
+        self.account.lamports()
+    }
+
+    // The part of the balance a signature can move: under `rent-collector` the rent isn't the
+    // signer's to spend
+    pub fn spendable(&self) -> Result<u64, ProgramError> {
+        #[cfg(feature = "rent-collector")]
+        let rent = crate::rent_collector::rent_share(self.account)?;
+        #[cfg(not(feature = "rent-collector"))]
+        let rent = 0;
+
+        Ok(self.balance() - rent)
+    }
+
+    // The key has signed something without closing the vault, so it must not be trusted again
+    pub fn is_locked(&self) -> bool {
+        self.state.burned
+    }
+
+    pub fn credit(&self, amount: u64) -> ProgramResult {
+        credit(self.account, amount)
+    }
+
+    // Move `amount` out of the vault, failing with InsufficientFunds beyond its balance
+    pub fn transfer(&self, to: &AccountInfo, amount: u64) -> ProgramResult {
+        let mut lamports = self
+            .account
+            .try_borrow_mut_lamports()
+            .map_err(map_borrow_error)?;
+        *lamports = lamports
+            .checked_sub(amount)
+            .ok_or(VaultError::InsufficientFunds)?;
+        drop(lamports);
+
+        credit(to, amount)
+    }
+
+    // Move whatever is left to `to` and close the vault
+    pub fn close_to(&self, to: &AccountInfo) -> ProgramResult {
+        credit(to, self.balance())?;
+        self.account.close().map_err(map_borrow_error)
+    }
+}
+
+/*
+    PDA seed of a vault whose address also depends on a passphrase: SHA-256 of the merklized
+    winternitz pubkey and `salt`, a hash of the passphrase. Open takes this seed in place of the
+    pubkey hash, so the vault can't be found or spent with the winternitz key alone. An all-zero
+    salt stands for no passphrase and returns the pubkey hash as is.
+*/
+pub fn vault_seed(pubkey_hash: &[u8; 32], salt: &[u8; 32]) -> [u8; 32] {
+    if salt == &[0; 32] {
+        return *pubkey_hash;
+    }
+
+    solana_nostd_sha256::hashv(&[pubkey_hash, salt])
+}
+
+/*
+    Passphrase salt of a spend. Built with `passphrase-salt`, every instruction that verifies a
+    vault signature takes the 32-byte salt after its other instruction data and checks the vault
+    against `vault_seed(recovered hash, salt)`. Without the feature no salt is sent and it stays
+    all zero, which leaves the recovered hash as is.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Salt(pub [u8; 32]);
+
+impl Salt {
+    #[cfg(feature = "passphrase-salt")]
+    pub const LEN: usize = 32;
+    #[cfg(not(feature = "passphrase-salt"))]
+    pub const LEN: usize = 0;
+
+    // Split the trailing salt off a spend's instruction data
+    pub fn split_off(data: &[u8]) -> Result<(&[u8], Self), ProgramError> {
+        let at = data
+            .len()
+            .checked_sub(Self::LEN)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        let (data, salt) = data.split_at(at);
+
+        let mut bytes = [0u8; 32];
+        bytes[..Self::LEN].copy_from_slice(salt);
+
+        Ok((data, Self(bytes)))
+    }
+
+    // The seed the vault spent with this salt is derived from
+    pub fn seed(&self, pubkey_hash: &[u8; 32]) -> [u8; 32] {
+        vault_seed(pubkey_hash, &self.0)
+    }
+}
+
+// Length of a winternitz signature, which every signed instruction's data starts with
+pub const SIGNATURE_LEN: usize = 896;
+
+/*
+    Split the signature off the front of a signed instruction's data. It's copied straight into
+    uninitialized memory rather than into a zeroed buffer first.
+*/
+pub fn split_signature(data: &[u8]) -> Result<(WinternitzSignature, &[u8]), ProgramError> {
+    if data.len() < SIGNATURE_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (signature, rest) = data.split_at(SIGNATURE_LEN);
+
+    let mut signature_array = MaybeUninit::<[u8; SIGNATURE_LEN]>::uninit();
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            signature.as_ptr(),
+            signature_array.as_mut_ptr() as *mut u8,
+            SIGNATURE_LEN,
+        );
+    }
+
+    Ok((
+        WinternitzSignature::from(unsafe { signature_array.assume_init() }),
+        rest,
+    ))
+}
+
+/*
+    Seeds to sign as the vault PDA of `pubkey_hash` in a CPI, e.g.
+    `let seeds = vault_signer_seeds(&hash, &bump).map(Seed::from);` then
+    `invoke_signed(&[Signer::from(&seeds)])`. The bump is taken by reference so the seeds can
+    borrow it.
+*/
+pub fn vault_signer_seeds<'a>(pubkey_hash: &'a [u8; 32], bump: &'a [u8; 1]) -> [&'a [u8]; 2] {
+    [pubkey_hash, bump]
+}
+
+/*
+    The outcome of a successful signature check: the vault seed recovered from the signature and
+    the bump it was checked against, so callers can log it or sign as the vault without recovering
+    the pubkey a second time.
+*/
+pub struct RecoveredVault {
+    pub hash: [u8; 32],
+    pub bump: [u8; 1],
+}
+
+impl RecoveredVault {
+    // Recover the merklized pubkey that signed `message` and check that it derives `vault`
+    pub fn verify(
+        signature: &WinternitzSignature,
+        message: &[u8],
+        bump: &[u8; 1],
+        salt: &Salt,
+        vault: &AccountInfo,
+    ) -> Result<Self, ProgramError> {
+        Self::check(salt.seed(&Self::recover(signature, message)?), bump, vault)
+    }
+
+    // The merklized pubkey that signed `message`, once the transaction can afford recovering it
+    pub fn recover(
+        signature: &WinternitzSignature,
+        message: &[u8],
+    ) -> Result<[u8; 32], ProgramError> {
+        crate::compute::check_recover_budget()?;
+
+        Ok(signature.recover_pubkey(message).merklize())
+    }
+
+    /*
+        Fast PDA equivalence check of a recovered vault seed. Deriving with crate::ID binds the
+        signature to vaults of this program: a hash whose PDA under another program ID matches the
+        vault is still rejected.
+    */
+    pub fn check(
+        hash: [u8; 32],
+        bump: &[u8; 1],
+        vault: &AccountInfo,
+    ) -> Result<Self, ProgramError> {
+        if solana_nostd_sha256::hashv(&[
+            hash.as_ref(),
+            bump.as_ref(),
+            crate::ID.as_ref(),
+            b"ProgramDerivedAddress",
+        ])
+        .ne(vault.key())
+        {
+            #[cfg(feature = "verbose-logs")]
+            crate::logs::log_pda_mismatch(&hash, vault.key());
+
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        Ok(Self { hash, bump: *bump })
+    }
+}
+
+pub fn credit(account: &AccountInfo, amount: u64) -> ProgramResult {
+    let mut lamports = account.try_borrow_mut_lamports().map_err(map_borrow_error)?;
+    *lamports = lamports
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    Ok(())
+}
+
+/*
+    Crediting an account that doesn't exist yet creates it. The runtime treats any zero-lamport
+    account as nonexistent, whatever owner it was last recorded with, so the new account is a
+    system account with no data, and it must end up rent-exempt or the whole transaction fails.
+    Check that up front with a clear error. Existing accounts are rent-exempt already, and any
+    owner's account can be credited; it keeps its owner and data.
+*/
+pub fn check_recipient(account: &AccountInfo, amount: u64) -> ProgramResult {
+    if account.lamports() == 0
+        && amount > 0
+        && amount < Rent::get()?.minimum_balance(account.data_len())
+    {
+        return Err(VaultError::RecipientNotRentExempt.into());
+    }
+
+    Ok(())
+}
+
+/*
+    The refund account of a split may be a new vault the funds roll over into, and an empty refund
+    account can't be told apart from an unopened vault address. So a remainder paid to an empty
+    account must cover a vault's rent-exempt minimum, not just a bare system account's, or the
+    rollover would leave the new vault's address under-rented.
+*/
+pub fn check_refund(account: &AccountInfo, remainder: u64) -> ProgramResult {
+    if account.lamports() == 0
+        && remainder > 0
+        && remainder < Rent::get()?.minimum_balance(VaultState::LEN)
+    {
+        return Err(VaultError::RecipientNotRentExempt.into());
+    }
+
+    Ok(())
+}
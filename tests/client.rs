@@ -0,0 +1,516 @@
+use pinocchio::program_error::ProgramError;
+use quantum_vault_pinocchio::{
+    client::{
+        canonical_bump, check_account_order, close_instruction_data, close_instruction_size,
+        explain, parse_vault_account, parse_vault_accounts, preview_split, spendable_balance,
+        split_instruction_data, split_instruction_size, v0_message_with_lookup_table,
+        vault_address, vault_lifecycle, vault_spendability, verify_vault_matches,
+        AccountOrderError, Spendability, VaultAccounts, VaultLifecycle, MAX_TRANSACTION_SIZE,
+    },
+    close::CloseVaultInstructionData,
+    error::VaultError,
+    split::SplitVaultInstructionData,
+    state::VaultState,
+    vault::{vault_seed, Salt},
+};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    message::{AccountMeta, AddressLookupTableAccount, Instruction, Message},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, VersionedTransaction},
+};
+use solana_winternitz::privkey::WinternitzPrivkey;
+
+// Serialized length of the only instruction in a message built from `ix`
+fn compiled_len(ix: Instruction) -> usize {
+    let message = Message::new(&[ix], Some(&Pubkey::new_unique()));
+    bincode::serialize(&message.instructions[0]).unwrap().len()
+}
+
+#[test]
+pub fn test_parse_vault_account() {
+    let state = VaultState::new([7u8; 32], [254], [9u8; 32]);
+    let data = state.to_bytes();
+
+    assert_eq!(parse_vault_account(&data), Ok(state));
+}
+
+#[test]
+pub fn test_parse_vault_accounts_batch() {
+    let states: Vec<VaultState> = (0..=255u8)
+        .step_by(51)
+        .map(|i| {
+            let mut state = VaultState::new([i; 32], [255 - i], [i.wrapping_mul(7); 32]);
+            state.burned = i % 2 == 1;
+            state
+        })
+        .collect();
+    let data: Vec<[u8; VaultState::LEN]> = states.iter().map(VaultState::to_bytes).collect();
+
+    // Fields sit at the same offsets whatever their values
+    for (state, data) in states.iter().zip(&data) {
+        let hash = &data[VaultState::HASH_OFFSET..VaultState::HASH_OFFSET + 32];
+        let opener = &data[VaultState::OPENER_OFFSET..VaultState::OPENER_OFFSET + 32];
+        assert_eq!(hash, state.hash);
+        assert_eq!(data[VaultState::BUMP_OFFSET], state.bump[0]);
+        assert_eq!(opener, state.opener);
+        assert_eq!(data[VaultState::BURNED_OFFSET], state.burned as u8);
+    }
+
+    let mut batch: Vec<Option<&[u8]>> = data.iter().map(|data| Some(&data[..])).collect();
+    batch.push(None);
+    batch.push(Some(&[0u8; 3]));
+
+    let mut expected: Vec<Option<VaultState>> = states.into_iter().map(Some).collect();
+    expected.extend([None, None]);
+    assert_eq!(parse_vault_accounts(&batch), expected);
+}
+
+#[test]
+pub fn test_parse_vault_account_rejects_corrupted_discriminator() {
+    let mut data = VaultState::new([7u8; 32], [254], [9u8; 32]).to_bytes();
+    data[0] = 0xff;
+
+    assert_eq!(
+        parse_vault_account(&data),
+        Err(VaultError::InvalidDiscriminator)
+    );
+}
+
+#[test]
+pub fn test_parse_vault_account_rejects_invalid_burned_flag() {
+    let mut data = VaultState::new([7u8; 32], [254], [9u8; 32]).to_bytes();
+    data[VaultState::LEN - 1] = 2;
+
+    assert_eq!(
+        parse_vault_account(&data),
+        Err(VaultError::InvalidVaultState)
+    );
+}
+
+#[test]
+pub fn test_parse_vault_account_rejects_unknown_version() {
+    let state = VaultState::new([7u8; 32], [254], [9u8; 32]);
+    assert_eq!(state.version, VaultState::VERSION);
+    assert_eq!(parse_vault_account(&state.to_bytes()), Ok(state));
+
+    // A future layout is rejected rather than misread by a v1 reader
+    let mut data = state.to_bytes();
+    data[1] = VaultState::VERSION + 1;
+    assert_eq!(
+        parse_vault_account(&data),
+        Err(VaultError::UnsupportedStateVersion)
+    );
+}
+
+#[test]
+pub fn test_parse_vault_account_rejects_wrong_length() {
+    let data = VaultState::new([7u8; 32], [254], [9u8; 32]).to_bytes();
+
+    assert_eq!(
+        parse_vault_account(&data[..VaultState::LEN - 1]),
+        Err(VaultError::InvalidVaultState)
+    );
+    assert_eq!(parse_vault_account(&[]), Err(VaultError::InvalidVaultState));
+}
+
+#[test]
+pub fn test_split_instruction_size_matches_serialized_length() {
+    let mut data = vec![1u8]; // Discriminator
+    data.extend_from_slice(&[0xab; 896]); // Signature
+    data.push(255); // Bump
+    data.extend_from_slice(&1_000u64.to_le_bytes()); // Amount
+
+    let ix = Instruction {
+        program_id: Pubkey::new_from_array(quantum_vault_pinocchio::ID),
+        accounts: vec![
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+        ],
+        data,
+    };
+
+    assert_eq!(split_instruction_size(), compiled_len(ix));
+    assert!(split_instruction_size() < MAX_TRANSACTION_SIZE);
+}
+
+#[test]
+pub fn test_close_instruction_size_matches_serialized_length() {
+    let mut data = vec![2u8]; // Discriminator
+    data.extend_from_slice(&[0xab; 896]); // Signature
+    data.push(255); // Bump
+
+    let ix = Instruction {
+        program_id: Pubkey::new_from_array(quantum_vault_pinocchio::ID),
+        accounts: vec![
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+        ],
+        data,
+    };
+
+    assert_eq!(close_instruction_size(), compiled_len(ix));
+    assert!(close_instruction_size() < MAX_TRANSACTION_SIZE);
+}
+
+#[test]
+pub fn test_spendable_balance() {
+    let rent = Rent::default();
+    let vault_rent = rent.minimum_balance(VaultState::LEN);
+
+    for state_size in [0, VaultState::LEN, 1024] {
+        let reserve = rent.minimum_balance(state_size);
+        assert_eq!(spendable_balance(0, state_size, &rent), 0);
+        assert_eq!(spendable_balance(reserve - 1, state_size, &rent), 0);
+        assert_eq!(spendable_balance(reserve, state_size, &rent), 0);
+        assert_eq!(spendable_balance(reserve + 1, state_size, &rent), 1);
+        assert_eq!(
+            spendable_balance(reserve + 5_000_000_000, state_size, &rent),
+            5_000_000_000
+        );
+        assert_eq!(
+            spendable_balance(u64::MAX, state_size, &rent),
+            u64::MAX - reserve
+        );
+    }
+
+    // A bigger state keeps more back
+    assert!(rent.minimum_balance(1024) > vault_rent);
+    assert_eq!(
+        spendable_balance(vault_rent + 10, VaultState::LEN, &rent),
+        10
+    );
+}
+
+#[test]
+pub fn test_preview_split_reports_overflow() {
+    let rent = Rent::default();
+    let vault_lamports = 10 * rent.minimum_balance(VaultState::LEN);
+
+    // Crediting either account past u64::MAX fails the split instead of wrapping
+    assert_eq!(
+        preview_split(vault_lamports, 1, u64::MAX, 0, &rent),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+    assert_eq!(
+        preview_split(vault_lamports, 0, 0, u64::MAX, &rent),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+    assert_eq!(
+        preview_split(vault_lamports, vault_lamports + 1, 0, 0, &rent),
+        Err(VaultError::InsufficientFunds.into())
+    );
+}
+
+#[test]
+pub fn test_vault_spendability() {
+    let rent = 1_356_720;
+    let open = VaultState::new([7u8; 32], [254], [9u8; 32]);
+    let mut burned = open;
+    burned.burned = true;
+
+    assert_eq!(
+        vault_spendability(rent + 1, &open.to_bytes(), rent),
+        Spendability::Spendable
+    );
+    assert_eq!(
+        vault_spendability(rent, &open.to_bytes(), rent),
+        Spendability::Unfunded
+    );
+    assert_eq!(
+        vault_spendability(rent + 1, &burned.to_bytes(), rent),
+        Spendability::Burned
+    );
+
+    // Closed vaults have no data and no lamports
+    assert_eq!(vault_spendability(0, &[], rent), Spendability::NotOpen);
+    assert_eq!(
+        vault_spendability(0, &open.to_bytes(), rent),
+        Spendability::NotOpen
+    );
+}
+
+#[test]
+pub fn test_vault_lifecycle() {
+    let rent = 1_356_720;
+    let open = VaultState::new([7u8; 32], [254], [9u8; 32]);
+    let mut burned = open;
+    burned.burned = true;
+
+    let vault = |lamports, state: &VaultState| Account {
+        lamports,
+        data: state.to_bytes().to_vec(),
+        owner: Pubkey::new_from_array(quantum_vault_pinocchio::ID),
+        executable: false,
+        rent_epoch: 0,
+    };
+
+    assert_eq!(
+        vault_lifecycle(Some(&vault(rent, &open)), rent),
+        VaultLifecycle::OpenUnfunded
+    );
+    assert_eq!(
+        vault_lifecycle(Some(&vault(rent + 1, &open)), rent),
+        VaultLifecycle::OpenFunded
+    );
+    // A burned vault is still open, funded or not
+    assert_eq!(
+        vault_lifecycle(Some(&vault(rent, &burned)), rent),
+        VaultLifecycle::Burned
+    );
+    assert_eq!(
+        vault_lifecycle(Some(&vault(rent + 1, &burned)), rent),
+        VaultLifecycle::Burned
+    );
+
+    // A closed vault is gone, which looks the same as a vault that was never opened
+    assert_eq!(vault_lifecycle(None, rent), VaultLifecycle::Uninitialized);
+    assert_eq!(
+        vault_lifecycle(Some(&vault(0, &open)), rent),
+        VaultLifecycle::Uninitialized
+    );
+
+    // Lamports sent to an unopened vault address sit in a system account
+    let prefunded = Account::new(rent, 0, &Pubkey::default());
+    assert_eq!(
+        vault_lifecycle(Some(&prefunded), rent),
+        VaultLifecycle::Uninitialized
+    );
+}
+
+#[test]
+pub fn test_explain_vault_errors() {
+    let errors = [
+        VaultError::InvalidVaultState,
+        VaultError::InvalidDiscriminator,
+        VaultError::KeyNotBurned,
+        VaultError::VaultNotEmpty,
+        VaultError::InvalidRentCollector,
+        VaultError::AccountAliasing,
+        VaultError::InvalidCommitment,
+        VaultError::UnsupportedAbiVersion,
+        VaultError::VaultAlreadyExists,
+        VaultError::MessageTooLong,
+        VaultError::InsufficientFunds,
+        VaultError::VaultAlreadyClosed,
+        VaultError::UnsupportedStateVersion,
+        VaultError::NonCanonicalBump,
+        VaultError::InvalidAuthorization,
+        VaultError::InsufficientComputeBudget,
+        VaultError::RecipientNotRentExempt,
+        VaultError::CommitmentNotExpired,
+    ];
+
+    let messages: Vec<&str> = errors.iter().map(|e| explain(*e as u32)).collect();
+    for (error, message) in errors.iter().zip(&messages) {
+        assert!(!message.is_empty(), "{:?} has no message", error);
+        assert_ne!(*message, explain(u32::MAX), "{:?} isn't mapped", error);
+    }
+
+    let mut distinct = messages.clone();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), messages.len());
+}
+
+#[test]
+pub fn test_split_v0_message_with_lookup_table_fits_transaction() {
+    let payer = Keypair::new();
+    let (vault, split, refund) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+
+    let mut data = vec![1u8]; // Discriminator
+    data.extend_from_slice(&[0xab; 896]); // Signature
+    data.push(255); // Bump
+    data.extend_from_slice(&1_000u64.to_le_bytes()); // Amount
+
+    let split_ix = Instruction {
+        program_id: Pubkey::new_from_array(quantum_vault_pinocchio::ID),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(split, false),
+            AccountMeta::new(refund, false),
+        ],
+        data,
+    };
+
+    // SetComputeUnitLimit and SetComputeUnitPrice
+    let compute_budget = Pubkey::from_str_const("ComputeBudget111111111111111111111111111111");
+    let mut limit_data = vec![2u8];
+    limit_data.extend_from_slice(&quantum_vault_pinocchio::SPLIT_CU.to_le_bytes());
+    let mut price_data = vec![3u8];
+    price_data.extend_from_slice(&1_000u64.to_le_bytes());
+    let instructions = [
+        Instruction::new_with_bytes(compute_budget, &limit_data, vec![]),
+        Instruction::new_with_bytes(compute_budget, &price_data, vec![]),
+        split_ix,
+    ];
+
+    let lookup_table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: vec![vault, split, refund],
+    };
+    let message = v0_message_with_lookup_table(
+        &payer.pubkey(),
+        &instructions,
+        &lookup_table,
+        Hash::new_unique(),
+    )
+    .unwrap();
+    let v0_tx = VersionedTransaction::try_new(message, &[&payer]).unwrap();
+    let v0_size = bincode::serialize(&v0_tx).unwrap().len();
+
+    let legacy_tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::new_unique(),
+    );
+    let legacy_size = bincode::serialize(&legacy_tx).unwrap().len();
+
+    assert!(v0_size <= MAX_TRANSACTION_SIZE);
+    assert!(v0_size < legacy_size);
+}
+
+#[test]
+pub fn test_canonical_bump_matches_find_program_address() {
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let program_id = Pubkey::new_from_array(quantum_vault_pinocchio::ID);
+    let (expected_address, expected_bump) = Pubkey::find_program_address(&[&hash], &program_id);
+
+    assert_eq!(canonical_bump(&hash), expected_bump);
+    assert_eq!(vault_address(&hash), (expected_address, expected_bump));
+
+    // The bump recorded at open is the same one
+    let state = VaultState::new(hash, [expected_bump], [9u8; 32]);
+    let parsed = parse_vault_account(&state.to_bytes()).unwrap();
+    assert_eq!(parsed.bump, [canonical_bump(&hash)]);
+}
+
+#[test]
+pub fn test_split_instruction_data_parses() {
+    let vault_keypair = WinternitzPrivkey::generate();
+    let salt = Salt([5; 32]);
+    let data = split_instruction_data(vault_keypair.sign(b"split"), 253, 42, &salt);
+
+    assert_eq!(data[0], 1);
+    let parsed = SplitVaultInstructionData::try_from(&data[1..])
+        .unwrap_or_else(|_| panic!("failed to parse split instruction data"));
+    assert_eq!(parsed.bump, [253]);
+    assert_eq!(u64::from_le_bytes(parsed.amount), 42);
+    // The salt is only sent when the program takes one
+    assert_eq!(parsed.salt.0[..Salt::LEN], salt.0[..Salt::LEN]);
+}
+
+#[test]
+pub fn test_close_instruction_data_parses() {
+    let vault_keypair = WinternitzPrivkey::generate();
+    let salt = Salt([5; 32]);
+    let data = close_instruction_data(vault_keypair.sign(b"close"), 253, &salt);
+
+    assert_eq!(data[0], 2);
+    let parsed = CloseVaultInstructionData::try_from(&data[1..])
+        .unwrap_or_else(|_| panic!("failed to parse close instruction data"));
+    assert_eq!(parsed.bump, [253]);
+    assert_eq!(parsed.salt.0[..Salt::LEN], salt.0[..Salt::LEN]);
+}
+
+#[test]
+pub fn test_verify_vault_matches() {
+    let vault_keypair = WinternitzPrivkey::generate();
+    let (vault, bump) = vault_address(&vault_keypair.pubkey().merklize());
+
+    let no_salt = Salt::default();
+
+    assert!(verify_vault_matches(&vault, &vault_keypair.pubkey(), bump, &no_salt));
+
+    let other_keypair = WinternitzPrivkey::generate();
+    assert!(!verify_vault_matches(&vault, &other_keypair.pubkey(), bump, &no_salt));
+    assert!(!verify_vault_matches(
+        &vault,
+        &vault_keypair.pubkey(),
+        bump.wrapping_sub(1),
+        &no_salt
+    ));
+
+    // A vault opened under a passphrase only matches with its salt
+    let salt = Salt([5; 32]);
+    let (salted_vault, salted_bump) =
+        vault_address(&vault_seed(&vault_keypair.pubkey().merklize(), &salt.0));
+    assert!(verify_vault_matches(
+        &salted_vault,
+        &vault_keypair.pubkey(),
+        salted_bump,
+        &salt
+    ));
+    assert!(!verify_vault_matches(
+        &salted_vault,
+        &vault_keypair.pubkey(),
+        salted_bump,
+        &no_salt
+    ));
+}
+
+// Under rent-collector split takes the collector as a fourth account
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_check_account_order() {
+    let vault = Pubkey::new_unique();
+    let split = Pubkey::new_unique();
+    let refund = Pubkey::new_unique();
+    let expected = VaultAccounts::Split {
+        vault,
+        split,
+        refund,
+    };
+
+    let accounts = vec![
+        AccountMeta::new(vault, false),
+        AccountMeta::new(split, false),
+        AccountMeta::new(refund, false),
+    ];
+    assert_eq!(check_account_order(&accounts, &expected), Ok(()));
+    assert_eq!(expected.to_account_metas(), accounts);
+
+    // Split and refund swapped
+    let swapped = vec![
+        AccountMeta::new(vault, false),
+        AccountMeta::new(refund, false),
+        AccountMeta::new(split, false),
+    ];
+    assert_eq!(
+        check_account_order(&swapped, &expected),
+        Err(AccountOrderError::Mismatch {
+            index: 1,
+            expected: AccountMeta::new(split, false),
+            found: AccountMeta::new(refund, false),
+        })
+    );
+
+    // A read-only refund can't be credited
+    let read_only = vec![
+        AccountMeta::new(vault, false),
+        AccountMeta::new(split, false),
+        AccountMeta::new_readonly(refund, false),
+    ];
+    assert!(matches!(
+        check_account_order(&read_only, &expected),
+        Err(AccountOrderError::Mismatch { index: 2, .. })
+    ));
+
+    assert_eq!(
+        check_account_order(&accounts[..2], &expected),
+        Err(AccountOrderError::WrongCount {
+            expected: 3,
+            found: 2,
+        })
+    );
+}
@@ -0,0 +1,433 @@
+use std::mem::MaybeUninit;
+
+use pinocchio::{
+    account_info::AccountInfo, entrypoint::deserialize, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use quantum_vault_pinocchio::{
+    close::CloseVaultInstructionData,
+    close_proportional::CloseProportionalInstructionData,
+    error::VaultError,
+    process_instruction,
+    rotate::RotateVaultInstructionData,
+    split::SplitVaultInstructionData,
+    split_many::SplitManyInstructionData,
+    state::VaultState,
+    store_authorization::StoreAuthorizationInstructionData,
+    vault::{RecoveredVault, Salt, Vault},
+    ABI_VERSION, ID, MAX_INSTRUCTION_DATA_LEN,
+};
+use solana_winternitz::privkey::WinternitzPrivkey;
+
+const MAX_ACCOUNTS: usize = 4;
+// Room the runtime leaves after each account's data for reallocs
+const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+struct TestAccount {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+/*
+    Serialize accounts and instruction data the way the runtime hands them to an entrypoint, then
+    deserialize them with pinocchio into AccountInfos. The buffer is leaked so the AccountInfos
+    stay valid for the rest of the test.
+*/
+fn account_infos(
+    accounts: &[TestAccount],
+    instruction_data: &[u8],
+) -> (Vec<AccountInfo>, &'static [u8]) {
+    let mut input = Vec::new();
+    input.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+    for account in accounts {
+        input.push(u8::MAX); // not a duplicate
+        input.push(account.is_signer as u8);
+        input.push(account.is_writable as u8);
+        input.push(0); // executable
+        input.extend_from_slice(&[0; 4]); // padding
+        input.extend_from_slice(&account.key);
+        input.extend_from_slice(&account.owner);
+        input.extend_from_slice(&account.lamports.to_le_bytes());
+        input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&account.data);
+        input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        input.resize(input.len().next_multiple_of(8), 0);
+        input.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+    }
+    input.extend_from_slice(&(instruction_data.len() as u64).to_le_bytes());
+    input.extend_from_slice(instruction_data);
+    input.extend_from_slice(&ID);
+
+    // The runtime's input is 8-byte aligned
+    let mut aligned = vec![0u64; input.len().div_ceil(8)];
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, input.len())
+    };
+    buffer.copy_from_slice(&input);
+    let input = Box::leak(aligned.into_boxed_slice()).as_mut_ptr() as *mut u8;
+
+    let mut infos: [MaybeUninit<AccountInfo>; MAX_ACCOUNTS] =
+        std::array::from_fn(|_| MaybeUninit::uninit());
+    let (_, count, data) = unsafe { deserialize::<MAX_ACCOUNTS>(input, &mut infos) };
+    let infos = infos[..count]
+        .iter()
+        .map(|info| unsafe { info.assume_init_read() })
+        .collect();
+
+    (infos, data)
+}
+
+#[test]
+pub fn test_dispatch_rejects_missing_and_unknown_discriminators() {
+    let (accounts, _) = account_infos(&[], &[]);
+
+    assert_eq!(
+        process_instruction(&ID, &accounts, &[]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        process_instruction(&ID, &accounts, &[0x0f]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // Diagnose's slot is empty unless it's built in
+    #[cfg(not(feature = "diagnose"))]
+    assert_eq!(
+        process_instruction(&ID, &accounts, &[0x06, 0]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+pub fn test_dispatch_rejects_unsupported_abi_version() {
+    let (accounts, _) = account_infos(&[], &[]);
+
+    assert_eq!(
+        process_instruction(&ID, &accounts, &[(ABI_VERSION + 1) << 4]),
+        Err(VaultError::UnsupportedAbiVersion.into())
+    );
+}
+
+#[test]
+pub fn test_dispatch_rejects_other_program_id() {
+    let (accounts, _) = account_infos(&[], &[]);
+
+    assert_eq!(
+        process_instruction(&[7; 32], &accounts, &[0]),
+        Err(ProgramError::IncorrectProgramId)
+    );
+}
+
+// `count` accounts, a funded vault followed by empty writable system accounts
+fn funded_accounts(count: usize) -> Vec<AccountInfo> {
+    let vault = TestAccount {
+        key: [1; 32],
+        owner: ID,
+        lamports: 5_000_000,
+        data: VaultState::new([7; 32], [254], [9; 32]).to_bytes().to_vec(),
+        is_signer: true,
+        is_writable: true,
+    };
+    let others = (2..=count as u8).map(|key| TestAccount {
+        key: [key; 32],
+        owner: [0; 32],
+        lamports: 0,
+        data: vec![],
+        is_signer: false,
+        is_writable: true,
+    });
+
+    account_infos(&std::iter::once(vault).chain(others).collect::<Vec<_>>(), &[]).0
+}
+
+#[test]
+pub fn test_dispatch_rejects_oversized_instruction_data() {
+    let accounts = funded_accounts(3);
+
+    let mut data = vec![0u8; 10 * 1024];
+    data[0] = 1; // split
+    assert_eq!(
+        process_instruction(&ID, &accounts, &data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // One byte past the limit is enough
+    data.truncate(MAX_INSTRUCTION_DATA_LEN + 1);
+    assert_eq!(
+        process_instruction(&ID, &accounts, &data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(accounts[0].lamports(), 5_000_000);
+}
+
+#[test]
+pub fn test_max_instruction_data_len_is_the_longest_instruction() {
+    let longest = [
+        SplitVaultInstructionData::LEN,
+        CloseVaultInstructionData::LEN,
+        StoreAuthorizationInstructionData::LEN,
+        RotateVaultInstructionData::LEN,
+        SplitManyInstructionData::MAX_LEN,
+        CloseProportionalInstructionData::MAX_LEN,
+    ]
+    .into_iter()
+    .max()
+    .unwrap();
+    assert_eq!(MAX_INSTRUCTION_DATA_LEN, 1 + longest);
+
+    // The longest instruction still leaves room in a transaction for its accounts and signature
+    assert!(MAX_INSTRUCTION_DATA_LEN < 1232);
+}
+
+// A lone discriminator hands its parser an empty payload, which must be rejected, not indexed
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_dispatch_rejects_discriminator_without_payload() {
+    // Open, split and close, each with the accounts it expects
+    for (discriminator, count) in [(0u8, 3), (1, 3), (2, 2)] {
+        let accounts = funded_accounts(count);
+
+        assert_eq!(
+            process_instruction(&ID, &accounts, &[discriminator]),
+            Err(ProgramError::InvalidInstructionData),
+            "discriminator {}",
+            discriminator
+        );
+        assert_eq!(accounts[0].lamports(), 5_000_000);
+    }
+}
+
+// A vault holding 5_000_000 lamports and a valid close of it to an empty refund account
+fn close_fixture() -> (Vec<AccountInfo>, &'static [u8]) {
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+    let bump = 255;
+    let vault = solana_nostd_sha256::hashv(&[
+        hash.as_ref(),
+        &[bump],
+        ID.as_ref(),
+        b"ProgramDerivedAddress",
+    ]);
+    let refund = [9u8; 32];
+
+    let mut message = [0u8; 33];
+    message[0] = 2;
+    message[1..].copy_from_slice(&refund);
+    let signature: [u8; 896] = vault_keypair.sign(&message).into();
+
+    let mut instruction_data = vec![2u8];
+    instruction_data.extend_from_slice(&signature);
+    instruction_data.push(bump);
+
+    account_infos(
+        &[
+            TestAccount {
+                key: vault,
+                owner: ID,
+                lamports: 5_000_000,
+                data: VaultState::new(hash, [bump], [1u8; 32]).to_bytes().to_vec(),
+                is_signer: false,
+                is_writable: true,
+            },
+            TestAccount {
+                key: refund,
+                owner: [0; 32],
+                lamports: 0,
+                data: vec![],
+                is_signer: false,
+                is_writable: true,
+            },
+        ],
+        &instruction_data,
+    )
+}
+
+#[test]
+pub fn test_dispatch_close() {
+    let (accounts, data) = close_fixture();
+
+    process_instruction(&ID, &accounts, data).unwrap();
+
+    assert_eq!(accounts[0].lamports(), 0);
+    assert_eq!(accounts[1].lamports(), 5_000_000);
+
+    // Closing also hands the account back to the system program with no data
+    assert!(accounts[0].is_owned_by(&[0; 32]));
+    assert_eq!(accounts[0].data_len(), 0);
+}
+
+#[test]
+pub fn test_dispatch_close_maps_borrow_failure_to_aliasing() {
+    let (accounts, data) = close_fixture();
+
+    // Hold the refund's lamports the way an aliased role would, so close can't borrow them
+    let refund_lamports = accounts[1].try_borrow_lamports().unwrap();
+
+    assert_eq!(
+        process_instruction(&ID, &accounts, data),
+        Err(VaultError::AccountAliasing.into())
+    );
+
+    drop(refund_lamports);
+    assert_eq!(accounts[0].lamports(), 5_000_000);
+    assert_eq!(accounts[1].lamports(), 0);
+}
+
+// A vault account holding `lamports` and an empty system account
+fn vault_fixture(state: VaultState, lamports: u64) -> Vec<AccountInfo> {
+    account_infos(
+        &[
+            TestAccount {
+                key: [1; 32],
+                owner: ID,
+                lamports,
+                data: state.to_bytes().to_vec(),
+                is_signer: false,
+                is_writable: true,
+            },
+            TestAccount {
+                key: [2; 32],
+                owner: [0; 32],
+                lamports: 0,
+                data: vec![],
+                is_signer: false,
+                is_writable: true,
+            },
+        ],
+        &[],
+    )
+    .0
+}
+
+#[test]
+pub fn test_vault_parses_owned_accounts_only() {
+    let state = VaultState::new([7; 32], [254], [9; 32]);
+    let accounts = vault_fixture(state, 5_000_000);
+
+    let vault = Vault::try_from(&accounts[0]).unwrap_or_else(|_| panic!("vault didn't parse"));
+    assert_eq!(vault.state, state);
+    assert_eq!(vault.balance(), 5_000_000);
+    assert!(!vault.is_locked());
+
+    assert!(matches!(
+        Vault::try_from(&accounts[1]),
+        Err(ProgramError::InvalidAccountOwner)
+    ));
+
+    let mut burned = state;
+    burned.burned = true;
+    let accounts = vault_fixture(burned, 5_000_000);
+    let vault = Vault::try_from(&accounts[0]).unwrap_or_else(|_| panic!("vault didn't parse"));
+    assert!(vault.is_locked());
+}
+
+#[test]
+pub fn test_vault_moves_lamports() {
+    let accounts = vault_fixture(VaultState::new([7; 32], [254], [9; 32]), 5_000_000);
+    let vault = Vault::try_from(&accounts[0]).unwrap_or_else(|_| panic!("vault didn't parse"));
+    let other = &accounts[1];
+
+    vault.credit(1_000).unwrap();
+    assert_eq!(vault.balance(), 5_001_000);
+
+    vault.transfer(other, 1_000_000).unwrap();
+    assert_eq!(vault.balance(), 4_001_000);
+    assert_eq!(other.lamports(), 1_000_000);
+
+    assert_eq!(
+        vault.transfer(other, 4_001_001),
+        Err(VaultError::InsufficientFunds.into())
+    );
+    assert_eq!(vault.balance(), 4_001_000);
+
+    vault.close_to(other).unwrap();
+    assert_eq!(accounts[0].lamports(), 0);
+    assert_eq!(accounts[0].data_len(), 0);
+    assert_eq!(other.lamports(), 5_001_000);
+}
+
+#[test]
+pub fn test_recovered_vault_carries_the_recovered_hash() {
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+    let bump = [255];
+    let state = VaultState::new(hash, bump, [1; 32]);
+    let (accounts, _) = account_infos(
+        &[TestAccount {
+            key: solana_nostd_sha256::hashv(&[
+                hash.as_ref(),
+                bump.as_ref(),
+                ID.as_ref(),
+                b"ProgramDerivedAddress",
+            ]),
+            owner: ID,
+            lamports: 5_000_000,
+            data: state.to_bytes().to_vec(),
+            is_signer: false,
+            is_writable: true,
+        }],
+        &[],
+    );
+
+    let message = [2u8; 33];
+    let signature = vault_keypair.sign(&message);
+
+    let recovered =
+        RecoveredVault::verify(&signature, &message, &bump, &Salt::default(), &accounts[0])
+            .unwrap_or_else(|_| panic!("signature didn't verify"));
+    assert_eq!(recovered.hash, hash);
+    assert_eq!(recovered.bump, bump);
+
+    // The same signature doesn't derive a vault at any other address
+    let other = vault_fixture(state, 5_000_000);
+    assert!(matches!(
+        RecoveredVault::verify(&signature, &message, &bump, &Salt::default(), &other[0]),
+        Err(ProgramError::MissingRequiredSignature)
+    ));
+}
+
+// Close takes a rent collector as well under rent-collector
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_vault_rejects_wrong_data_length() {
+    let state = VaultState::new([7; 32], [254], [9; 32]).to_bytes();
+    let close_data = vec![2u8; 1 + CloseVaultInstructionData::LEN];
+
+    for data in [state[..VaultState::LEN - 1].to_vec(), [&state[..], &[0]].concat()] {
+        let (accounts, _) = account_infos(
+            &[
+                TestAccount {
+                    key: [1; 32],
+                    owner: ID,
+                    lamports: 5_000_000,
+                    data,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                TestAccount {
+                    key: [2; 32],
+                    owner: [0; 32],
+                    lamports: 0,
+                    data: vec![],
+                    is_signer: false,
+                    is_writable: true,
+                },
+            ],
+            &[],
+        );
+
+        assert!(matches!(
+            Vault::try_from(&accounts[0]),
+            Err(error) if error == VaultError::InvalidVaultState.into()
+        ));
+        assert_eq!(
+            process_instruction(&ID, &accounts, &close_data),
+            Err(VaultError::InvalidVaultState.into())
+        );
+        assert_eq!(accounts[0].lamports(), 5_000_000);
+    }
+}
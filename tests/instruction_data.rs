@@ -0,0 +1,168 @@
+use pinocchio::program_error::ProgramError;
+use quantum_vault_pinocchio::{
+    close::{CloseVault, CloseVaultInstructionData},
+    open::{OpenVault, OpenVaultInstructionData},
+    split::{SplitVault, SplitVaultInstructionData},
+    vault::SIGNATURE_LEN,
+};
+use solana_winternitz::{privkey::WinternitzPrivkey, signature::WinternitzSignature};
+
+/*
+    Executable specification of the instruction wire formats. Each layout is spelled out with
+    named offsets, including the discriminator, and must be accepted by the parser as is. A
+    change to any of these is a breaking change for every client.
+*/
+
+#[test]
+pub fn test_open_instruction_data_accepts_exact_length() {
+    let mut data = vec![7u8; 32]; // hash
+    data.push(254); // bump
+
+    let instruction_data = OpenVaultInstructionData::try_from(data.as_slice())
+        .unwrap_or_else(|_| panic!("failed to parse open instruction data"));
+    assert_eq!(instruction_data.hash, [7u8; 32]);
+    assert_eq!(instruction_data.bump, [254]);
+}
+
+#[test]
+pub fn test_open_instruction_data_rejects_truncated_hash() {
+    let mut data = vec![7u8; 31]; // hash missing its last byte
+    data.push(254); // bump
+
+    assert!(matches!(
+        OpenVaultInstructionData::try_from(data.as_slice()),
+        Err(ProgramError::InvalidInstructionData)
+    ));
+}
+
+#[test]
+pub fn test_open_instruction_data_rejects_oversized_hash() {
+    let mut data = vec![7u8; 33]; // hash with an extra byte
+    data.push(254); // bump
+
+    assert!(matches!(
+        OpenVaultInstructionData::try_from(data.as_slice()),
+        Err(ProgramError::InvalidInstructionData)
+    ));
+}
+
+#[test]
+pub fn test_open_instruction_data_rejects_signature_sized_payload() {
+    // A split payload sent with the open discriminator by mistake
+    let mut data = vec![7u8; SIGNATURE_LEN]; // signature
+    data.push(254); // bump
+    data.extend_from_slice(&42u64.to_le_bytes()); // amount
+    assert_eq!(data.len(), 905);
+
+    assert!(matches!(
+        OpenVaultInstructionData::try_from(data.as_slice()),
+        Err(ProgramError::InvalidInstructionData)
+    ));
+}
+
+#[test]
+pub fn test_split_instruction_data_accepts_exact_length() {
+    let mut data = vec![7u8; SIGNATURE_LEN]; // signature
+    data.push(254); // bump
+    data.extend_from_slice(&42u64.to_le_bytes()); // amount
+    assert_eq!(data.len(), SplitVaultInstructionData::LEN);
+
+    let instruction_data = SplitVaultInstructionData::try_from(data.as_slice())
+        .unwrap_or_else(|_| panic!("failed to parse split instruction data"));
+    assert_eq!(instruction_data.bump, [254]);
+    assert_eq!(u64::from_le_bytes(instruction_data.amount), 42);
+}
+
+#[test]
+pub fn test_split_instruction_data_rejects_trailing_byte() {
+    let mut data = vec![7u8; SIGNATURE_LEN]; // signature
+    data.push(254); // bump
+    data.extend_from_slice(&42u64.to_le_bytes()); // amount
+    data.push(0); // trailing byte
+
+    assert!(matches!(
+        SplitVaultInstructionData::try_from(data.as_slice()),
+        Err(ProgramError::InvalidInstructionData)
+    ));
+}
+
+#[test]
+pub fn test_open_wire_format() {
+    const DISCRIMINATOR: usize = 0;
+    const HASH: usize = 1; // 32 bytes
+    const BUMP: usize = 33; // 1 byte
+    const LEN: usize = 34;
+
+    let mut data = [0u8; LEN];
+    data[DISCRIMINATOR] = *OpenVault::DISCRIMINATOR;
+    data[HASH..BUMP].copy_from_slice(&[7u8; 32]);
+    data[BUMP] = 254;
+
+    assert_eq!(data[DISCRIMINATOR], 0);
+    let parsed = OpenVaultInstructionData::try_from(&data[HASH..])
+        .unwrap_or_else(|_| panic!("failed to parse open wire format"));
+    assert_eq!(parsed.hash, [7u8; 32]);
+    assert_eq!(parsed.bump, [254]);
+}
+
+#[test]
+pub fn test_split_wire_format() {
+    const DISCRIMINATOR: usize = 0;
+    const SIGNATURE: usize = 1; // 896 bytes
+    const BUMP: usize = 897; // 1 byte
+    const AMOUNT: usize = 898; // 8 bytes, little-endian
+    const LEN: usize = 906;
+
+    let mut data = [0u8; LEN];
+    data[DISCRIMINATOR] = *SplitVault::DISCRIMINATOR;
+    data[SIGNATURE..BUMP].copy_from_slice(&[7u8; 896]);
+    data[BUMP] = 254;
+    data[AMOUNT..LEN].copy_from_slice(&42u64.to_le_bytes());
+
+    assert_eq!(data[DISCRIMINATOR], 1);
+    let parsed = SplitVaultInstructionData::try_from(&data[SIGNATURE..])
+        .unwrap_or_else(|_| panic!("failed to parse split wire format"));
+    assert_eq!(parsed.bump, [254]);
+    assert_eq!(u64::from_le_bytes(parsed.amount), 42);
+}
+
+#[test]
+pub fn test_close_wire_format() {
+    const DISCRIMINATOR: usize = 0;
+    const SIGNATURE: usize = 1; // 896 bytes
+    const BUMP: usize = 897; // 1 byte
+    const LEN: usize = 898;
+
+    let mut data = [0u8; LEN];
+    data[DISCRIMINATOR] = *CloseVault::DISCRIMINATOR;
+    data[SIGNATURE..BUMP].copy_from_slice(&[7u8; 896]);
+    data[BUMP] = 254;
+
+    assert_eq!(data[DISCRIMINATOR], 2);
+    let parsed = CloseVaultInstructionData::try_from(&data[SIGNATURE..])
+        .unwrap_or_else(|_| panic!("failed to parse close wire format"));
+    assert_eq!(parsed.bump, [254]);
+}
+
+// The parsers rebuild the signature from its 896 raw bytes, so the bytes must carry all of it
+#[test]
+pub fn test_signature_bytes_round_trip() {
+    let privkey = WinternitzPrivkey::generate();
+    let message = b"quantum vault round trip";
+
+    let signature = privkey.sign(message);
+    let expected = signature.recover_pubkey(message).merklize();
+
+    let bytes: [u8; 896] = signature.into();
+    let rebuilt = WinternitzSignature::from(bytes);
+    assert_eq!(rebuilt.recover_pubkey(message).merklize(), expected);
+    assert_eq!(expected, privkey.pubkey().merklize());
+
+    // Same through the split parser, which copies the bytes out of the instruction data
+    let mut data = bytes.to_vec();
+    data.push(254); // bump
+    data.extend_from_slice(&42u64.to_le_bytes()); // amount
+    let parsed = SplitVaultInstructionData::try_from(data.as_slice())
+        .unwrap_or_else(|_| panic!("failed to parse split instruction data"));
+    assert_eq!(parsed.siganture.recover_pubkey(message).merklize(), expected);
+}
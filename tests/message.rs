@@ -0,0 +1,75 @@
+use quantum_vault_pinocchio::{error::VaultError, message::SpendMessage, split::SplitVault};
+use solana_winternitz::privkey::WinternitzPrivkey;
+
+#[test]
+pub fn test_spend_message_with_four_recipients() {
+    let recipients: Vec<(u64, [u8; 32])> = (1..=4u8)
+        .map(|i| (i as u64 * 1_000, [i; 32]))
+        .collect();
+    let refund = [0xee; 32];
+
+    let mut message = SpendMessage::new(1);
+    for (amount, recipient) in &recipients {
+        message
+            .push_recipient(&amount.to_le_bytes(), recipient)
+            .unwrap();
+    }
+    message.push_refund(&refund).unwrap();
+
+    let bytes = message.as_bytes();
+    assert_eq!(bytes.len(), 1 + 4 * SpendMessage::RECIPIENT_LEN + 32);
+    assert_eq!(bytes[0], 1);
+    for (i, (amount, recipient)) in recipients.iter().enumerate() {
+        let offset = SpendMessage::recipient_offset(i);
+        assert_eq!(bytes[offset..offset + 8], amount.to_le_bytes());
+        assert_eq!(bytes[offset + 8..offset + 40], *recipient);
+    }
+    assert_eq!(bytes[bytes.len() - 32..], refund);
+
+    // The signature over the variable length message recovers the signer's key
+    let vault_keypair = WinternitzPrivkey::generate();
+    let signature = vault_keypair.sign(bytes);
+    assert_eq!(
+        signature.recover_pubkey(bytes).merklize(),
+        vault_keypair.pubkey().merklize()
+    );
+}
+
+#[test]
+pub fn test_spend_message_rejects_too_many_recipients() {
+    let mut message = SpendMessage::new(1);
+    for _ in 0..SpendMessage::MAX_RECIPIENTS {
+        message.push_recipient(&1u64.to_le_bytes(), &[1; 32]).unwrap();
+    }
+
+    assert_eq!(
+        message.push_recipient(&1u64.to_le_bytes(), &[1; 32]),
+        Err(VaultError::MessageTooLong)
+    );
+
+    // The refund still fits after the last recipient
+    message.push_refund(&[2; 32]).unwrap();
+    assert_eq!(message.as_bytes().len(), SpendMessage::MAX_LEN);
+}
+
+#[test]
+pub fn test_split_message_matches_client_layout() {
+    let cases = [
+        (0u64, [0u8; 32], [0u8; 32]),
+        (1, [1; 32], [2; 32]),
+        (500_000, [0xab; 32], [0xab; 32]),
+        (u64::MAX, [0xff; 32], [0x7f; 32]),
+    ];
+
+    for (amount, split, refund) in cases {
+        // Client side: [discriminator | amount | split | refund], as signed by the tests
+        let mut expected = [0u8; 73];
+        expected[0] = *SplitVault::DISCRIMINATOR;
+        expected[1..9].copy_from_slice(&amount.to_le_bytes());
+        expected[9..41].copy_from_slice(&split);
+        expected[41..73].copy_from_slice(&refund);
+
+        let message = SplitVault::message(&amount.to_le_bytes(), &split, &refund).unwrap();
+        assert_eq!(message.as_bytes(), expected.as_slice());
+    }
+}
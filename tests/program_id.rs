@@ -0,0 +1,27 @@
+use quantum_vault_pinocchio::{ID, LOCALNET_ID};
+
+#[cfg(not(any(feature = "devnet", feature = "mainnet")))]
+#[test]
+pub fn test_localnet_program_id_is_active() {
+    assert_eq!(ID, LOCALNET_ID);
+}
+
+#[cfg(feature = "devnet")]
+#[test]
+pub fn test_devnet_program_id_is_active() {
+    assert_eq!(
+        ID,
+        pinocchio_pubkey::from_str(env!("QUANTUM_VAULT_DEVNET_ID"))
+    );
+    assert_ne!(ID, LOCALNET_ID);
+}
+
+#[cfg(feature = "mainnet")]
+#[test]
+pub fn test_mainnet_program_id_is_active() {
+    assert_eq!(
+        ID,
+        pinocchio_pubkey::from_str(env!("QUANTUM_VAULT_MAINNET_ID"))
+    );
+    assert_ne!(ID, LOCALNET_ID);
+}
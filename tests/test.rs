@@ -1,5 +1,17 @@
-use litesvm::LiteSVM;
+use litesvm::{types::TransactionResult, LiteSVM};
+use proptest::prelude::*;
+use quantum_vault_pinocchio::{
+    close_proportional::CloseProportional,
+    error::VaultError,
+    message::SpendMessage,
+    split_bps::SplitBps,
+    split_many::SplitMany,
+    state::{Authorization, Commitment, VaultState},
+    vault::vault_signer_seeds,
+    ABI_VERSION, CLOSE_CU, OPEN_CU, SPLIT_CU,
+};
 use solana_sdk::{
+    account::Account,
     message::{AccountMeta, Instruction},
     native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
@@ -11,105 +23,178 @@ use solana_system_interface::program;
 use solana_winternitz::privkey::WinternitzPrivkey;
 use std::str::FromStr;
 
-#[test]
-pub fn test_quantum_vault_refund() {
+#[cfg(feature = "diagnose")]
+use quantum_vault_pinocchio::diagnose::Diagnosis;
+
+// Create an SVM with the program deployed and a funded fee payer
+fn setup_svm() -> (LiteSVM, Keypair) {
     let mut svm = LiteSVM::new();
 
     let payer = Keypair::new();
     svm.airdrop(&payer.pubkey(), 10 * LAMPORTS_PER_SOL)
         .expect("failed to airdrop");
 
-    let program_id_bytes: [u8; 32] = [
-        0x0f, 0x1e, 0x6b, 0x14, 0x21, 0xc0, 0x4a, 0x07, 0x04, 0x31, 0x26, 0x5c, 0x19, 0xc5, 0xbb,
-        0xee, 0x19, 0x92, 0xba, 0xe8, 0xaf, 0xd1, 0xcd, 0x07, 0x8e, 0xf8, 0xaf, 0x70, 0x47, 0xdc,
-        0x11, 0xf7,
-    ];
-    let program_id = Pubkey::from(program_id_bytes);
-    let program_bytes = include_bytes!("../../target/deploy/quantum_vault_pinocchio.so");
-
-    svm.add_program(program_id, program_bytes)
+    svm.add_program(program_id(), &program_bytes())
         .expect("failed to add program");
 
+    (svm, payer)
+}
+
+// The program built by `cargo build-sbf`, loaded at runtime so a missing build fails with advice
+// instead of a compile error
+fn program_bytes() -> Vec<u8> {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/deploy/quantum_vault_pinocchio.so"
+    );
+    std::fs::read(path).unwrap_or_else(|err| {
+        panic!("failed to read {path} ({err}): build the program with `cargo build-sbf` first")
+    })
+}
+
+fn program_id() -> Pubkey {
+    Pubkey::from(quantum_vault_pinocchio::ID)
+}
+
+// Open a vault under a fresh winternitz keypair and deposit `lamports` on top of its rent
+fn setup_funded_vault(svm: &mut LiteSVM, lamports: u64) -> (WinternitzPrivkey, Pubkey, u8) {
+    let funder = Keypair::new();
+    svm.airdrop(&funder.pubkey(), lamports + LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
     let vault_keypair = WinternitzPrivkey::generate();
     let vault_pubkey_hash = vault_keypair.pubkey().merklize();
 
     // Find PDA for the vault
     let (vault_address, bump) =
-        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &program_id);
+        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &program_id());
 
-    // 1. Test open instruction
+    // 1. Open the vault
     let mut open_ix_data = vec![0u8]; // Discriminator
     open_ix_data.extend_from_slice(&vault_pubkey_hash);
     open_ix_data.push(bump);
 
     let open_ix = Instruction {
-        program_id,
+        program_id: program_id(),
         accounts: vec![
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(funder.pubkey(), true),
             AccountMeta::new(vault_address, false),
             AccountMeta::new_readonly(program::ID, false),
         ],
         data: open_ix_data,
     };
 
-    let tx = Transaction::new_signed_with_payer(
-        &[open_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        svm.latest_blockhash(),
-    );
-
-    let result = svm.send_transaction(tx);
-    if let Ok(response) = &result {
-        let logs = response.pretty_logs();
-        println!("open transaction logs:\n{}", logs);
-    } else if let Err(e) = &result {
-        eprintln!("open transaction failed: {:?}", e);
-    }
-    result.expect("Failed to open vault");
+    send(svm, &[open_ix], &funder, "open").expect("Failed to open vault");
 
     // 2. Fund the vault
     let transfer_ix = Instruction {
         program_id: program::ID,
         accounts: vec![
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(funder.pubkey(), true),
             AccountMeta::new(vault_address, false),
         ],
         data: {
             let mut data = vec![2, 0, 0, 0]; // Transfer instruction discriminator
-            data.extend_from_slice(&(5 * LAMPORTS_PER_SOL).to_le_bytes());
+            data.extend_from_slice(&lamports.to_le_bytes());
             data
         },
     };
 
+    send(svm, &[transfer_ix], &funder, "fund").expect("Failed to fund vault");
+
+    assert_balance_with_rent(svm, &vault_address, lamports);
+
+    (vault_keypair, vault_address, bump)
+}
+
+// Compute budget instruction for Winternitz signature verification
+fn compute_budget_ix(units: u32) -> Instruction {
+    Instruction {
+        program_id: Pubkey::from_str("ComputeBudget111111111111111111111111111111").unwrap(),
+        accounts: vec![],
+        data: {
+            let mut data = vec![2, 0, 0, 0]; // SetComputeUnitLimit instruction discriminator
+            data.extend_from_slice(&units.to_le_bytes());
+            data
+        },
+    }
+}
+
+// Sign and send a transaction, printing its logs or failure
+fn send(
+    svm: &mut LiteSVM,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    label: &str,
+) -> TransactionResult {
     let tx = Transaction::new_signed_with_payer(
-        &[transfer_ix],
+        instructions,
         Some(&payer.pubkey()),
-        &[&payer],
+        &[payer],
         svm.latest_blockhash(),
     );
+
     let result = svm.send_transaction(tx);
     if let Ok(response) = &result {
         let logs = response.pretty_logs();
-        println!("fund transaction logs:\n{}", logs);
+        println!("{} transaction logs:\n{}", label, logs);
     } else if let Err(e) = &result {
-        eprintln!("fund transaction failed: {:?}", e);
+        eprintln!("{} transaction failed: {:?}", label, e);
     }
-    result.expect("Failed to fund vault");
+    result
+}
+
+// Assert a transaction failed with the given vault error
+fn assert_vault_error(result: TransactionResult, error: VaultError) {
+    let err = match result {
+        Ok(_) => panic!("transaction succeeded, expected {:?}", error),
+        Err(failed) => failed.err,
+    };
+    let expected = format!("Custom({}))", error as u32);
+    assert!(
+        format!("{:?}", err).ends_with(&expected),
+        "expected {:?}, got {:?}",
+        error,
+        err
+    );
+}
+
+// Assert an account holds `expected_funds` on top of the rent-exempt minimum for a vault's state
+fn assert_balance_with_rent(svm: &LiteSVM, account: &Pubkey, expected_funds: u64) {
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+    let lamports = svm.get_account(account).map_or(0, |account| account.lamports);
+    assert_eq!(
+        lamports,
+        expected_funds + rent,
+        "expected {} + {} rent lamports in {}",
+        expected_funds,
+        rent,
+        account
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_refund() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
 
     let vault_account = svm.get_account(&vault_address).unwrap();
-    assert_eq!(vault_account.lamports, 5 * LAMPORTS_PER_SOL + 890880);
+    let vault_state = VaultState::try_from(vault_account.data.as_slice()).unwrap();
+    assert_eq!(vault_state.hash, vault_keypair.pubkey().merklize());
+    assert_eq!(vault_state.bump, [bump]);
+    assert!(!vault_state.burned);
 
-    // 3. Test split instruction
+    // Split the vault
     let split_account = Keypair::new();
     let refund_account = Keypair::new();
     let split_amount = 2 * LAMPORTS_PER_SOL;
 
-    // Build the 72-byte message: [amount (8 bytes) | split_pubkey (32 bytes) | refund_pubkey (32 bytes)]
-    let mut message = [0u8; 72];
-    message[0..8].copy_from_slice(&split_amount.to_le_bytes());
-    message[8..40].copy_from_slice(split_account.pubkey().as_ref());
-    message[40..72].copy_from_slice(refund_account.pubkey().as_ref());
+    // Neither recipient exists on-chain before the split
+    assert!(svm.get_account(&split_account.pubkey()).is_none());
+    assert!(svm.get_account(&refund_account.pubkey()).is_none());
+
+    // Build the 73-byte message: [discriminator | amount (8 bytes) | split_pubkey (32 bytes) | refund_pubkey (32 bytes)]
+    let message = split_message(split_amount, &split_account.pubkey(), &refund_account.pubkey());
 
     // Sign the message with Winternitz private key
     let signature = vault_keypair.sign(&message);
@@ -121,7 +206,7 @@ pub fn test_quantum_vault_refund() {
     split_ix_data.extend_from_slice(&split_amount.to_le_bytes());
 
     let split_ix = Instruction {
-        program_id,
+        program_id: program_id(),
         accounts: vec![
             AccountMeta::new(vault_address, false),
             AccountMeta::new(split_account.pubkey(), false),
@@ -130,44 +215,29 @@ pub fn test_quantum_vault_refund() {
         data: split_ix_data,
     };
 
-    // add compute budget instruction for Winternitz signature verification
-    let compute_budget_ix = Instruction {
-        program_id: Pubkey::from_str("ComputeBudget111111111111111111111111111111").unwrap(),
-        accounts: vec![],
-        data: {
-            let mut data = vec![2, 0, 0, 0]; // SetComputeUnitLimit instruction discriminator
-            data.extend_from_slice(&1_400_000u32.to_le_bytes());
-            data
-        },
-    };
-
-    let tx = Transaction::new_signed_with_payer(
-        &[compute_budget_ix, split_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        svm.latest_blockhash(),
-    );
-
-    let result = svm.send_transaction(tx);
-    if let Ok(response) = &result {
-        let logs = response.pretty_logs();
-        println!("Split transaction logs:\n{}", logs);
-    } else if let Err(e) = &result {
-        eprintln!("Split transaction failed: {:?}", e);
-    }
-    result.expect("Failed to split vault");
+    send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), split_ix],
+        &payer,
+        "Split",
+    )
+    .expect("Failed to split vault");
 
     let split_account_info = svm.get_account(&split_account.pubkey()).unwrap();
     assert_eq!(split_account_info.lamports, split_amount);
 
-    let refund_account_info = svm.get_account(&refund_account.pubkey()).unwrap();
-    let split_account_info = svm.get_account(&split_account.pubkey()).unwrap();
-    assert_eq!(
-        refund_account_info.lamports,
-        5 * LAMPORTS_PER_SOL - split_amount + 890880
+    assert_balance_with_rent(
+        &svm,
+        &refund_account.pubkey(),
+        5 * LAMPORTS_PER_SOL - split_amount,
     );
+    let refund_account_info = svm.get_account(&refund_account.pubkey()).unwrap();
 
-    assert_eq!(split_account_info.lamports, split_amount);
+    // Crediting a nonexistent account creates it as an empty system account
+    assert_eq!(split_account_info.owner, program::ID);
+    assert!(split_account_info.data.is_empty());
+    assert_eq!(refund_account_info.owner, program::ID);
+    assert!(refund_account_info.data.is_empty());
 
     let vault_account_after = svm.get_account(&vault_address);
     assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
@@ -175,138 +245,3941 @@ pub fn test_quantum_vault_refund() {
 
 #[test]
 pub fn test_quantum_vault_close() {
-    let mut svm = LiteSVM::new();
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 3 * LAMPORTS_PER_SOL);
 
-    let payer = Keypair::new();
-    svm.airdrop(&payer.pubkey(), 10 * LAMPORTS_PER_SOL)
-        .expect("failed to airdrop");
+    // close instruction
+    let refund_account = Keypair::new();
+    assert!(svm.get_account(&refund_account.pubkey()).is_none());
 
-    let program_id_bytes: [u8; 32] = [
-        0x0f, 0x1e, 0x6b, 0x14, 0x21, 0xc0, 0x4a, 0x07, 0x04, 0x31, 0x26, 0x5c, 0x19, 0xc5, 0xbb,
-        0xee, 0x19, 0x92, 0xba, 0xe8, 0xaf, 0xd1, 0xcd, 0x07, 0x8e, 0xf8, 0xaf, 0x70, 0x47, 0xdc,
-        0x11, 0xf7,
-    ];
-    let program_id = Pubkey::from(program_id_bytes);
-    let program_bytes = include_bytes!("../../target/deploy/quantum_vault_pinocchio.so");
+    let signature = vault_keypair.sign(&close_message(&refund_account.pubkey()));
+    let signature_bytes: [u8; 896] = signature.into();
 
-    svm.add_program(program_id, program_bytes)
-        .expect("failed to add program");
+    let mut close_ix_data = vec![2u8]; // Discriminator
+    close_ix_data.extend_from_slice(&signature_bytes);
+    close_ix_data.push(bump);
 
-    let vault_keypair = WinternitzPrivkey::generate();
-    let vault_pubkey_hash = vault_keypair.pubkey().merklize();
+    let close_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(refund_account.pubkey(), false),
+        ],
+        data: close_ix_data,
+    };
 
-    let (vault_address, bump) =
-        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &program_id);
+    send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), close_ix],
+        &payer,
+        "Close",
+    )
+    .expect("Failed to close vault");
 
-    // 1. Open vault
-    let mut open_ix_data = vec![0u8];
-    open_ix_data.extend_from_slice(&vault_pubkey_hash);
-    open_ix_data.push(bump);
+    assert_balance_with_rent(&svm, &refund_account.pubkey(), 3 * LAMPORTS_PER_SOL);
+    let refund_account_info = svm.get_account(&refund_account.pubkey()).unwrap();
+    assert_eq!(refund_account_info.owner, program::ID);
+    assert!(refund_account_info.data.is_empty());
 
-    let open_ix = Instruction {
-        program_id,
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_close_to_two() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 3 * LAMPORTS_PER_SOL);
+
+    // Close to a funds account and a rent refund account
+    let funds_account = Keypair::new();
+    let rent_refund_account = Keypair::new();
+
+    // Build the 65-byte message: [discriminator | funds_pubkey (32 bytes) | rent_refund_pubkey (32 bytes)]
+    let mut message = [0u8; 65];
+    message[0] = 3;
+    message[1..33].copy_from_slice(funds_account.pubkey().as_ref());
+    message[33..65].copy_from_slice(rent_refund_account.pubkey().as_ref());
+
+    let signature = vault_keypair.sign(&message);
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut close_ix_data = vec![3u8]; // Discriminator
+    close_ix_data.extend_from_slice(&signature_bytes);
+    close_ix_data.push(bump);
+
+    let close_ix = Instruction {
+        program_id: program_id(),
         accounts: vec![
-            AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(vault_address, false),
-            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new(funds_account.pubkey(), false),
+            AccountMeta::new(rent_refund_account.pubkey(), false),
         ],
-        data: open_ix_data,
+        data: close_ix_data,
     };
 
-    let tx = Transaction::new_signed_with_payer(
-        &[open_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        svm.latest_blockhash(),
-    );
+    send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), close_ix],
+        &payer,
+        "Close to two",
+    )
+    .expect("Failed to close vault to two accounts");
 
-    let result = svm.send_transaction(tx);
-    if let Ok(response) = &result {
-        let logs = response.pretty_logs();
-        println!("open transaction logs:\n{}", logs);
-    } else if let Err(e) = &result {
-        eprintln!("open transaction failed: {:?}", e);
-    }
-    result.expect("Failed to open vault");
+    let funds_account_info = svm.get_account(&funds_account.pubkey()).unwrap();
+    assert_eq!(funds_account_info.lamports, 3 * LAMPORTS_PER_SOL);
 
-    // 2. Fund the vault
-    let transfer_ix = Instruction {
-        program_id: program::ID,
+    assert_balance_with_rent(&svm, &rent_refund_account.pubkey(), 0);
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_split_aliased_refund_is_atomic() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+
+    // Split with the vault aliased as the refund account
+    let split_account = Keypair::new();
+    let split_amount = 2 * LAMPORTS_PER_SOL;
+
+    let message = split_message(split_amount, &split_account.pubkey(), &vault_address);
+
+    let signature = vault_keypair.sign(&message);
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut split_ix_data = vec![1u8];
+    split_ix_data.extend_from_slice(&signature_bytes);
+    split_ix_data.push(bump);
+    split_ix_data.extend_from_slice(&split_amount.to_le_bytes());
+
+    let split_ix = Instruction {
+        program_id: program_id(),
         accounts: vec![
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(split_account.pubkey(), false),
             AccountMeta::new(vault_address, false),
         ],
-        data: {
-            let mut data = vec![2, 0, 0, 0]; // Transfer instruction discriminator
-            data.extend_from_slice(&(3 * LAMPORTS_PER_SOL).to_le_bytes());
-            data
-        },
+        data: split_ix_data,
     };
-    let tx = Transaction::new_signed_with_payer(
-        &[transfer_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        svm.latest_blockhash(),
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), split_ix],
+        &payer,
+        "Aliased split",
     );
+    assert!(result.is_err());
 
-    let result = svm.send_transaction(tx);
-    if let Ok(response) = &result {
-        let logs = response.pretty_logs();
-        println!("fund transaction logs:\n{}", logs);
-    } else if let Err(e) = &result {
-        eprintln!("fund transaction failed: {:?}", e);
-    }
-    result.expect("Failed to fund the vault");
+    // Nothing moved: the vault keeps its balance and the split account was never created
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+    assert!(svm.get_account(&split_account.pubkey()).is_none());
+}
 
-    // close instruction
-    let refund_account = Keypair::new();
+#[test]
+pub fn test_quantum_vault_prove_ownership() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
 
-    let signature = vault_keypair.sign(refund_account.pubkey().as_ref());
+    // Build the 33-byte message: [discriminator (1 byte) | challenge (32 bytes)]
+    let challenge = [42u8; 32];
+    let mut message = [0u8; 33];
+    message[0] = 4;
+    message[1..33].copy_from_slice(&challenge);
+
+    let signature = vault_keypair.sign(&message);
     let signature_bytes: [u8; 896] = signature.into();
 
-    let mut close_ix_data = vec![2u8]; // Discriminator
-    close_ix_data.extend_from_slice(&signature_bytes);
-    close_ix_data.push(bump);
+    let prove_ix = |challenge: &[u8; 32]| {
+        let mut prove_ix_data = vec![4u8]; // Discriminator
+        prove_ix_data.extend_from_slice(&signature_bytes);
+        prove_ix_data.push(bump);
+        prove_ix_data.extend_from_slice(challenge);
 
-    let close_ix = Instruction {
-        program_id,
+        Instruction {
+            program_id: program_id(),
+            accounts: vec![AccountMeta::new(vault_address, false)],
+            data: prove_ix_data,
+        }
+    };
+
+    // A proof over a different challenge doesn't validate against the vault
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), prove_ix(&[7u8; 32])],
+        &payer,
+        "Prove with wrong challenge",
+    );
+    assert!(result.is_err());
+
+    send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), prove_ix(&challenge)],
+        &payer,
+        "Prove",
+    )
+    .expect("Failed to prove vault ownership");
+
+    // Proving ownership doesn't move any funds, but burns the key
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+    assert!(
+        VaultState::try_from(vault_account_after.data.as_slice())
+            .unwrap()
+            .burned
+    );
+}
+
+// Burn a vault's key with an ownership proof
+fn prove_ownership(svm: &mut LiteSVM, payer: &Keypair, vault: (&WinternitzPrivkey, Pubkey, u8)) {
+    let (vault_keypair, vault_address, bump) = vault;
+
+    let challenge = [42u8; 32];
+    let mut message = [0u8; 33];
+    message[0] = 4;
+    message[1..33].copy_from_slice(&challenge);
+
+    let signature = vault_keypair.sign(&message);
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut prove_ix_data = vec![4u8]; // Discriminator
+    prove_ix_data.extend_from_slice(&signature_bytes);
+    prove_ix_data.push(bump);
+    prove_ix_data.extend_from_slice(&challenge);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    prove_ix_data.extend_from_slice(&[0; 32]);
+
+    let prove_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(vault_address, false)],
+        data: prove_ix_data,
+    };
+
+    send(svm, &[compute_budget_ix(1_400_000), prove_ix], payer, "Prove")
+        .expect("Failed to prove vault ownership");
+}
+
+fn reclaim_rent_ix(vault_address: Pubkey, collector: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
         accounts: vec![
             AccountMeta::new(vault_address, false),
-            AccountMeta::new(refund_account.pubkey(), false),
+            AccountMeta::new(collector, false),
         ],
-        data: close_ix_data,
-    };
+        data: vec![5u8], // Discriminator
+    }
+}
 
-    // Add compute budget instruction for Winternitz signature verification
-    let compute_budget_ix = Instruction {
-        program_id: Pubkey::from_str("ComputeBudget111111111111111111111111111111").unwrap(),
-        accounts: vec![],
-        data: {
-            let mut data = vec![2, 0, 0, 0]; // SetComputeUnitLimit instruction discriminator
-            data.extend_from_slice(&1_400_000u32.to_le_bytes());
-            data
-        },
-    };
+#[test]
+pub fn test_quantum_vault_reclaim_rent_from_burned_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 0);
 
-    let tx = Transaction::new_signed_with_payer(
-        &[compute_budget_ix, close_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        svm.latest_blockhash(),
+    prove_ownership(&mut svm, &payer, (&vault_keypair, vault_address, bump));
+
+    let vault_account = svm.get_account(&vault_address).unwrap();
+    let opener = Pubkey::from(
+        VaultState::try_from(vault_account.data.as_slice())
+            .unwrap()
+            .opener,
     );
+    let opener_balance_before = svm.get_account(&opener).unwrap().lamports;
 
-    let result = svm.send_transaction(tx);
-    if let Ok(response) = &result {
-        let logs = response.pretty_logs();
-        println!("Close transaction logs:\n{}", logs);
-    } else if let Err(e) = &result {
-        eprintln!("Close transaction failed: {:?}", e);
-    }
-    result.expect("Failed to close vault");
+    // The rent can only go back to the opener
+    let result = send(
+        &mut svm,
+        &[reclaim_rent_ix(vault_address, payer.pubkey())],
+        &payer,
+        "Reclaim rent to wrong collector",
+    );
+    assert!(result.is_err());
 
-    let refund_account_info = svm.get_account(&refund_account.pubkey()).unwrap();
-    assert_eq!(refund_account_info.lamports, 3 * LAMPORTS_PER_SOL + 890880);
+    send(
+        &mut svm,
+        &[reclaim_rent_ix(vault_address, opener)],
+        &payer,
+        "Reclaim rent",
+    )
+    .expect("Failed to reclaim rent");
+
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+    let opener_account = svm.get_account(&opener).unwrap();
+    assert_eq!(opener_account.lamports, opener_balance_before + rent);
 
     let vault_account_after = svm.get_account(&vault_address);
     assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
 }
+
+#[test]
+pub fn test_quantum_vault_reclaim_rent_rejects_active_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (_vault_keypair, vault_address, _bump) = setup_funded_vault(&mut svm, 0);
+
+    let vault_account = svm.get_account(&vault_address).unwrap();
+    let opener = Pubkey::from(
+        VaultState::try_from(vault_account.data.as_slice())
+            .unwrap()
+            .opener,
+    );
+
+    // The key was never burned, so the vault is still active
+    let result = send(
+        &mut svm,
+        &[reclaim_rent_ix(vault_address, opener)],
+        &payer,
+        "Reclaim rent from active vault",
+    );
+    assert!(result.is_err());
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_account.lamports);
+}
+
+#[test]
+pub fn test_quantum_vault_reclaim_rent_rejects_funded_burned_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    prove_ownership(&mut svm, &payer, (&vault_keypair, vault_address, bump));
+
+    let vault_account = svm.get_account(&vault_address).unwrap();
+    let opener = Pubkey::from(
+        VaultState::try_from(vault_account.data.as_slice())
+            .unwrap()
+            .opener,
+    );
+
+    // Funds above the rent still belong to the key holder
+    let result = send(
+        &mut svm,
+        &[reclaim_rent_ix(vault_address, opener)],
+        &payer,
+        "Reclaim rent from funded vault",
+    );
+    assert!(result.is_err());
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_account.lamports);
+}
+
+#[test]
+pub fn test_quantum_vault_open_fits_default_compute_budget() {
+    let (mut svm, payer) = setup_svm();
+
+    let vault_keypair = WinternitzPrivkey::generate();
+    let vault_pubkey_hash = vault_keypair.pubkey().merklize();
+    let (vault_address, bump) =
+        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &program_id());
+
+    let mut open_ix_data = vec![0u8]; // Discriminator
+    open_ix_data.extend_from_slice(&vault_pubkey_hash);
+    open_ix_data.push(bump);
+
+    let open_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: open_ix_data,
+    };
+
+    // Open doesn't verify a winternitz signature, so no compute budget instruction is needed
+    let response = send(&mut svm, &[open_ix], &payer, "Open without compute budget")
+        .expect("Failed to open vault with the default compute budget");
+
+    // Leave plenty of headroom under the 200,000 CU default
+    assert!(response.compute_units_consumed < 50_000);
+}
+
+#[cfg(feature = "diagnose")]
+#[test]
+pub fn test_quantum_vault_debug_recover_returns_hash() {
+    let (mut svm, payer) = setup_svm();
+
+    let vault_keypair = WinternitzPrivkey::generate();
+    let message = b"any message the client wants to check";
+
+    let signature = vault_keypair.sign(message);
+    let expected_hash = signature.recover_pubkey(message).merklize();
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut debug_ix_data = vec![6u8]; // Discriminator
+    debug_ix_data.extend_from_slice(&signature_bytes);
+    debug_ix_data.extend_from_slice(message);
+
+    let debug_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![],
+        data: debug_ix_data,
+    };
+
+    let response = send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), debug_ix],
+        &payer,
+        "Debug recover",
+    )
+    .expect("Failed to recover pubkey hash");
+
+    assert_eq!(response.return_data.program_id, program_id());
+    assert_eq!(response.return_data.data, expected_hash.to_vec());
+    assert_eq!(expected_hash, vault_keypair.pubkey().merklize());
+}
+
+#[cfg(feature = "diagnose")]
+// Diagnose a signature over `message` against a vault, returning the reason code and hash
+fn diagnose(
+    svm: &mut LiteSVM,
+    payer: &Keypair,
+    signer: &WinternitzPrivkey,
+    message: &[u8],
+    vault: (Pubkey, u8),
+) -> (Diagnosis, [u8; 32]) {
+    let signature: [u8; 896] = signer.sign(message).into();
+
+    let mut data = vec![6u8];
+    data.extend_from_slice(&signature);
+    data.push(vault.1);
+    data.extend_from_slice(message);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    let ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new_readonly(vault.0, false)],
+        data,
+    };
+    let response = send(svm, &[compute_budget_ix(1_400_000), ix], payer, "Diagnose")
+        .expect("diagnose failed");
+
+    let report = response.return_data.data;
+    let diagnosis = [
+        Diagnosis::Ok,
+        Diagnosis::BadLength,
+        Diagnosis::ZeroHash,
+        Diagnosis::PdaMismatch,
+        Diagnosis::AmountExceedsBalance,
+    ]
+    .into_iter()
+    .find(|diagnosis| *diagnosis as u8 == report[0])
+    .expect("unknown reason code");
+
+    (diagnosis, report[1..].try_into().unwrap())
+}
+
+#[cfg(feature = "diagnose")]
+#[test]
+pub fn test_quantum_vault_diagnose_reports_each_reason() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let balance = svm.get_account(&vault_address).unwrap().lamports;
+    let hash = vault_keypair.pubkey().merklize();
+    let recipient = Keypair::new().pubkey();
+    let vault = (vault_address, bump);
+
+    // Under `rent-collector` the vault's rent isn't the signer's to spend
+    #[cfg(feature = "rent-collector")]
+    let spendable = balance - svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+    #[cfg(not(feature = "rent-collector"))]
+    let spendable = balance;
+
+    let message = split_message(spendable, &recipient, &recipient);
+    assert_eq!(
+        diagnose(&mut svm, &payer, &vault_keypair, &message, vault),
+        (Diagnosis::Ok, hash)
+    );
+
+    // A split message missing its last byte
+    assert_eq!(
+        diagnose(&mut svm, &payer, &vault_keypair, &message[..72], vault),
+        (Diagnosis::BadLength, [0; 32])
+    );
+
+    // An address that was never opened
+    let unopened = (Keypair::new().pubkey(), bump);
+    assert_eq!(
+        diagnose(&mut svm, &payer, &vault_keypair, &message, unopened),
+        (Diagnosis::ZeroHash, [0; 32])
+    );
+
+    // Signed by the wrong key: the report carries that key's hash
+    let other_keypair = WinternitzPrivkey::generate();
+    assert_eq!(
+        diagnose(&mut svm, &payer, &other_keypair, &message, vault),
+        (Diagnosis::PdaMismatch, other_keypair.pubkey().merklize())
+    );
+
+    let message = split_message(spendable + 1, &recipient, &recipient);
+    assert_eq!(
+        diagnose(&mut svm, &payer, &vault_keypair, &message, vault),
+        (Diagnosis::AmountExceedsBalance, hash)
+    );
+
+    // Nothing moved
+    assert_eq!(svm.get_account(&vault_address).unwrap().lamports, balance);
+}
+
+// Message signed for a split: discriminator, amount, split pubkey and refund pubkey
+fn split_message(amount: u64, split: &Pubkey, refund: &Pubkey) -> [u8; 73] {
+    let mut message = [0u8; 73];
+    message[0] = 1;
+    message[1..9].copy_from_slice(&amount.to_le_bytes());
+    message[9..41].copy_from_slice(split.as_ref());
+    message[41..73].copy_from_slice(refund.as_ref());
+    message
+}
+
+// Message signed for a close: discriminator and refund pubkey
+fn close_message(refund: &Pubkey) -> [u8; 33] {
+    let mut message = [0u8; 33];
+    message[0] = 2;
+    message[1..33].copy_from_slice(refund.as_ref());
+    message
+}
+
+// Build a split instruction signed by the vault's winternitz key
+fn split_ix(
+    vault: (&WinternitzPrivkey, Pubkey, u8),
+    split: Pubkey,
+    refund: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (vault_keypair, vault_address, bump) = vault;
+
+    let message = split_message(amount, &split, &refund);
+
+    let signature = vault_keypair.sign(&message);
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut split_ix_data = vec![1u8];
+    split_ix_data.extend_from_slice(&signature_bytes);
+    split_ix_data.push(bump);
+    split_ix_data.extend_from_slice(&amount.to_le_bytes());
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    split_ix_data.extend_from_slice(&[0; 32]);
+
+    #[allow(unused_mut)]
+    let mut accounts = vec![
+        AccountMeta::new(vault_address, false),
+        AccountMeta::new(split, false),
+        AccountMeta::new(refund, false),
+    ];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: split_ix_data,
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_split_entire_balance_skips_refund() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(1_400_000),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                vault_balance,
+            ),
+        ],
+        &payer,
+        "Split entire balance",
+    )
+    .expect("Failed to split entire vault balance");
+
+    let split_account_info = svm.get_account(&split_account.pubkey()).unwrap();
+    assert_eq!(split_account_info.lamports, vault_balance);
+
+    // Nothing is left for the refund account, so it's never created
+    let refund_account_after = svm.get_account(&refund_account.pubkey());
+    assert!(refund_account_after.is_none() || refund_account_after.unwrap().lamports == 0);
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_recommended_compute_limits() {
+    let (mut svm, payer) = setup_svm();
+
+    // Open within OPEN_CU
+    let vault_keypair = WinternitzPrivkey::generate();
+    let vault_pubkey_hash = vault_keypair.pubkey().merklize();
+    let (vault_address, bump) =
+        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &program_id());
+
+    let mut open_ix_data = vec![0u8]; // Discriminator
+    open_ix_data.extend_from_slice(&vault_pubkey_hash);
+    open_ix_data.push(bump);
+
+    let open_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: open_ix_data,
+    };
+
+    send(
+        &mut svm,
+        &[compute_budget_ix(OPEN_CU), open_ix],
+        &payer,
+        "Open with OPEN_CU",
+    )
+    .expect("Failed to open vault within OPEN_CU");
+
+    // Split within SPLIT_CU
+    let vault = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault.0, vault.1, vault.2),
+                Keypair::new().pubkey(),
+                Keypair::new().pubkey(),
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split with SPLIT_CU",
+    )
+    .expect("Failed to split vault within SPLIT_CU");
+
+    // Close within CLOSE_CU
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let refund_account = Keypair::new();
+
+    let signature = vault_keypair.sign(&close_message(&refund_account.pubkey()));
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut close_ix_data = vec![2u8]; // Discriminator
+    close_ix_data.extend_from_slice(&signature_bytes);
+    close_ix_data.push(bump);
+
+    let close_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(refund_account.pubkey(), false),
+        ],
+        data: close_ix_data,
+    };
+
+    send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU), close_ix],
+        &payer,
+        "Close with CLOSE_CU",
+    )
+    .expect("Failed to close vault within CLOSE_CU");
+}
+
+#[test]
+pub fn test_quantum_vault_split_rejects_vault_as_split_account() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+    let refund_account = Keypair::new();
+
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(1_400_000),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                vault_address,
+                refund_account.pubkey(),
+                2 * LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split to vault",
+    );
+    assert_vault_error(result, VaultError::AccountAliasing);
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+    assert!(svm.get_account(&refund_account.pubkey()).is_none());
+}
+
+// Build an open, commit open or reveal open instruction for the given payer and vault
+fn open_ix(discriminator: u8, payer: &Pubkey, vault: &Pubkey, data: &[u8]) -> Instruction {
+    let commitment =
+        Pubkey::find_program_address(&[Commitment::SEED, vault.as_ref()], &program_id()).0;
+
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*vault, false),
+    ];
+    if discriminator != 0 {
+        accounts.push(AccountMeta::new(commitment, false));
+    }
+    accounts.push(AccountMeta::new_readonly(program::ID, false));
+
+    let mut ix_data = vec![discriminator];
+    ix_data.extend_from_slice(data);
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: ix_data,
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_commit_reveal_open_resists_front_running() {
+    let (mut svm, payer) = setup_svm();
+    let attacker = Keypair::new();
+    svm.airdrop(&attacker.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let (commitment_address, commitment_bump) = Pubkey::find_program_address(
+        &[Commitment::SEED, vault_address.as_ref()],
+        &program_id(),
+    );
+
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+
+    // Commit only reveals the vault address
+    send(
+        &mut svm,
+        &[open_ix(7, &payer.pubkey(), &vault_address, &[commitment_bump])],
+        &payer,
+        "Commit open",
+    )
+    .expect("Failed to commit open");
+
+    assert_balance_with_rent(&svm, &vault_address, 0);
+
+    // Once the hash is public, neither a plain open nor a reveal by someone else gets through
+    let result = send(
+        &mut svm,
+        &[open_ix(0, &attacker.pubkey(), &vault_address, &open_data)],
+        &attacker,
+        "Front-run open",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyExists);
+
+    let result = send(
+        &mut svm,
+        &[open_ix(8, &attacker.pubkey(), &vault_address, &open_data)],
+        &attacker,
+        "Front-run reveal",
+    );
+    assert_vault_error(result, VaultError::InvalidCommitment);
+
+    send(
+        &mut svm,
+        &[open_ix(8, &payer.pubkey(), &vault_address, &open_data)],
+        &payer,
+        "Reveal open",
+    )
+    .expect("Failed to reveal open");
+
+    let vault_account = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account.owner, program_id());
+    assert_balance_with_rent(&svm, &vault_address, 0);
+    assert_eq!(
+        VaultState::try_from(vault_account.data.as_slice()).unwrap(),
+        VaultState::new(hash, [bump], payer.pubkey().to_bytes())
+    );
+
+    let commitment_closed = svm
+        .get_account(&commitment_address)
+        .map_or(true, |account| account.lamports == 0);
+    assert!(commitment_closed);
+}
+
+#[test]
+pub fn test_quantum_vault_commit_open_rejects_non_canonical_bump() {
+    let (mut svm, payer) = setup_svm();
+    let attacker = Keypair::new();
+    svm.airdrop(&attacker.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let (_, commitment_bump) = Pubkey::find_program_address(
+        &[Commitment::SEED, vault_address.as_ref()],
+        &program_id(),
+    );
+
+    // Another bump that also derives a commitment address for the vault
+    let (other_commitment, other_bump) = (0..commitment_bump)
+        .rev()
+        .find_map(|bump| {
+            Pubkey::create_program_address(
+                &[Commitment::SEED, vault_address.as_ref(), &[bump]],
+                &program_id(),
+            )
+            .ok()
+            .map(|address| (address, bump))
+        })
+        .expect("no other commitment bump");
+
+    let mut ix = open_ix(7, &attacker.pubkey(), &vault_address, &[other_bump]);
+    ix.accounts[2] = AccountMeta::new(other_commitment, false);
+    let result = send(&mut svm, &[ix], &attacker, "Commit with another bump");
+    assert_vault_error(result, VaultError::NonCanonicalBump);
+    assert!(svm.get_account(&other_commitment).is_none());
+    assert!(svm.get_account(&vault_address).is_none());
+
+    // The owner's commitment is the only one the vault can have
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+    send(
+        &mut svm,
+        &[
+            open_ix(7, &payer.pubkey(), &vault_address, &[commitment_bump]),
+            open_ix(8, &payer.pubkey(), &vault_address, &open_data),
+        ],
+        &payer,
+        "Commit and reveal open",
+    )
+    .expect("Failed to commit and reveal open");
+    assert_eq!(svm.get_account(&vault_address).unwrap().owner, program_id());
+}
+
+// Build a cancel of a vault's commitment, which pays the commitment's rent to the vault address
+fn cancel_commitment_ix(vault: &Pubkey) -> Instruction {
+    let commitment =
+        Pubkey::find_program_address(&[Commitment::SEED, vault.as_ref()], &program_id()).0;
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(commitment, false),
+            AccountMeta::new(*vault, false),
+        ],
+        data: vec![9u8],
+    }
+}
+
+// Build a plain open of an address that already holds lamports, which needs the commitment PDA
+fn open_prefunded_ix(payer: &Pubkey, vault: &Pubkey, data: &[u8]) -> Instruction {
+    let commitment =
+        Pubkey::find_program_address(&[Commitment::SEED, vault.as_ref()], &program_id()).0;
+
+    let mut ix = open_ix(0, payer, vault, data);
+    ix.accounts.push(AccountMeta::new_readonly(commitment, false));
+    ix
+}
+
+// Warp past the expiry of the vault's current commitment
+fn expire_commitment(svm: &mut LiteSVM, vault: &Pubkey) {
+    let commitment =
+        Pubkey::find_program_address(&[Commitment::SEED, vault.as_ref()], &program_id()).0;
+    let data = svm.get_account(&commitment).unwrap().data;
+    let committed_slot = Commitment::try_from(data.as_slice()).unwrap().slot;
+
+    svm.warp_to_slot(committed_slot + Commitment::EXPIRY_SLOTS);
+    svm.expire_blockhash();
+}
+
+#[test]
+pub fn test_quantum_vault_squatted_commitment_costs_the_squatter() {
+    let (mut svm, payer) = setup_svm();
+    let attacker = Keypair::new();
+    svm.airdrop(&attacker.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let (commitment_address, commitment_bump) = Pubkey::find_program_address(
+        &[Commitment::SEED, vault_address.as_ref()],
+        &program_id(),
+    );
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+
+    // The attacker squats on the vault address before the owner commits
+    send(
+        &mut svm,
+        &[open_ix(7, &attacker.pubkey(), &vault_address, &[commitment_bump])],
+        &attacker,
+        "Squatting commit",
+    )
+    .expect("Failed to commit open");
+    let commitment = svm.get_account(&commitment_address).unwrap();
+    let vault_rent = svm.get_account(&vault_address).unwrap().lamports;
+
+    // While the commitment holds the address, the owner can neither commit nor open
+    let result = send(
+        &mut svm,
+        &[open_ix(7, &payer.pubkey(), &vault_address, &[commitment_bump])],
+        &payer,
+        "Commit after squatter",
+    );
+    assert!(result.is_err());
+
+    let result = send(
+        &mut svm,
+        &[open_prefunded_ix(&payer.pubkey(), &vault_address, &open_data)],
+        &payer,
+        "Open a committed address",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyExists);
+
+    // Nobody can cancel it early, not even the attacker to commit again
+    let result = send(
+        &mut svm,
+        &[cancel_commitment_ix(&vault_address)],
+        &attacker,
+        "Early cancel",
+    );
+    assert_vault_error(result, VaultError::CommitmentNotExpired);
+    assert_eq!(svm.get_account(&commitment_address).unwrap(), commitment);
+
+    // Squatting again after the expiry forfeits the commitment's rent to the vault address
+    expire_commitment(&mut svm, &vault_address);
+    send(
+        &mut svm,
+        &[
+            cancel_commitment_ix(&vault_address),
+            open_ix(7, &attacker.pubkey(), &vault_address, &[commitment_bump]),
+        ],
+        &attacker,
+        "Cancel and squat again",
+    )
+    .expect("Failed to squat again");
+    assert_eq!(
+        svm.get_account(&vault_address).unwrap().lamports,
+        vault_rent + commitment.lamports
+    );
+
+    // Once that one expires too, the owner cancels it and opens the pre-funded address with a
+    // plain open in the same transaction, so nobody can squat in between
+    expire_commitment(&mut svm, &vault_address);
+    send(
+        &mut svm,
+        &[
+            cancel_commitment_ix(&vault_address),
+            open_prefunded_ix(&payer.pubkey(), &vault_address, &open_data),
+        ],
+        &payer,
+        "Cancel expired commitment and open",
+    )
+    .expect("Failed to open after cancelling the expired commitment");
+
+    let vault_account = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account.owner, program_id());
+    assert_eq!(vault_account.lamports, vault_rent + 2 * commitment.lamports);
+    assert_eq!(
+        VaultState::try_from(vault_account.data.as_slice()).unwrap(),
+        VaultState::new(hash, [bump], payer.pubkey().to_bytes())
+    );
+
+    // The opened vault can't be committed to anymore
+    let result = send(
+        &mut svm,
+        &[open_ix(7, &attacker.pubkey(), &vault_address, &[commitment_bump])],
+        &attacker,
+        "Commit to an open vault",
+    );
+    assert_program_error(result, "AccountAlreadyInitialized");
+}
+
+#[test]
+pub fn test_quantum_vault_split_credits_survive_close() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+
+    // Recipients that already hold lamports, so a credit overwritten or undone by close shows up
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+    svm.airdrop(&split_account.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+    svm.airdrop(&refund_account.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    let split_amount = 2 * LAMPORTS_PER_SOL;
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(1_400_000),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                split_amount,
+            ),
+        ],
+        &payer,
+        "Split",
+    )
+    .expect("Failed to split vault");
+
+    let vault_lamports_after = svm
+        .get_account(&vault_address)
+        .map_or(0, |account| account.lamports);
+    assert_eq!(vault_lamports_after, 0);
+
+    let split_lamports = svm.get_account(&split_account.pubkey()).unwrap().lamports;
+    let refund_lamports = svm.get_account(&refund_account.pubkey()).unwrap().lamports;
+    assert_eq!(split_lamports, LAMPORTS_PER_SOL + split_amount);
+    assert_eq!(
+        refund_lamports,
+        LAMPORTS_PER_SOL + vault_balance - split_amount
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_rejects_unexpected_program_id() {
+    let (mut svm, payer) = setup_svm();
+
+    // Same program deployed under an address other than the one it was built for
+    let other_program_id = Pubkey::new_unique();
+    svm.add_program(other_program_id, &program_bytes())
+        .expect("failed to add program");
+
+    let vault_pubkey_hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) =
+        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &other_program_id);
+
+    let mut open_ix_data = vec![0u8];
+    open_ix_data.extend_from_slice(&vault_pubkey_hash);
+    open_ix_data.push(bump);
+
+    let open_ix = Instruction {
+        program_id: other_program_id,
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: open_ix_data,
+    };
+
+    let result = send(&mut svm, &[open_ix], &payer, "Open under other program id");
+    let err = result.expect_err("open under an unexpected program id succeeded");
+    assert!(format!("{:?}", err.err).contains("IncorrectProgramId"));
+    assert!(svm.get_account(&vault_address).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_close_rejects_redirected_refund() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+    let intended_refund = Keypair::new();
+    let attacker_refund = Keypair::new();
+
+    // Signed over the intended refund account, but submitted with the attacker's
+    let signature = vault_keypair.sign(&close_message(&intended_refund.pubkey()));
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut close_ix_data = vec![2u8]; // Discriminator
+    close_ix_data.extend_from_slice(&signature_bytes);
+    close_ix_data.push(bump);
+
+    let close_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(attacker_refund.pubkey(), false),
+        ],
+        data: close_ix_data,
+    };
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), close_ix],
+        &payer,
+        "Redirected close",
+    );
+    let err = result.expect_err("close to a refund account that wasn't signed over succeeded");
+    assert!(format!("{:?}", err.err).contains("MissingRequiredSignature"));
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+    assert!(svm.get_account(&attacker_refund.pubkey()).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_split_to_same_split_and_refund_account() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    let recipient = Keypair::new();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(1_400_000),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                recipient.pubkey(),
+                recipient.pubkey(),
+                2 * LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split to same account",
+    )
+    .expect("Failed to split to the same split and refund account");
+
+    // amount + remainder: the whole vault balance, counted exactly once
+    let recipient_info = svm.get_account(&recipient.pubkey()).unwrap();
+    assert_eq!(recipient_info.lamports, vault_balance);
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_rejects_unsupported_abi_version() {
+    let (mut svm, payer) = setup_svm();
+
+    let vault_pubkey_hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) =
+        Pubkey::find_program_address(&[vault_pubkey_hash.as_ref()], &program_id());
+
+    // Open (discriminator 0), tagged with the next ABI version in the high nibble
+    let mut open_ix_data = vec![(ABI_VERSION + 1) << 4];
+    open_ix_data.extend_from_slice(&vault_pubkey_hash);
+    open_ix_data.push(bump);
+
+    let open_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: open_ix_data,
+    };
+
+    let result = send(&mut svm, &[open_ix], &payer, "Open with future ABI version");
+    assert_vault_error(result, VaultError::UnsupportedAbiVersion);
+    assert!(svm.get_account(&vault_address).is_none());
+}
+
+// Build a close instruction signed by the vault's winternitz key
+fn close_ix(vault: (&WinternitzPrivkey, Pubkey, u8), refund: Pubkey) -> Instruction {
+    let (vault_keypair, vault_address, bump) = vault;
+
+    let signature = vault_keypair.sign(&close_message(&refund));
+    let signature_bytes: [u8; 896] = signature.into();
+
+    let mut close_ix_data = vec![2u8];
+    close_ix_data.extend_from_slice(&signature_bytes);
+    close_ix_data.push(bump);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    close_ix_data.extend_from_slice(&[0; 32]);
+
+    #[allow(unused_mut)]
+    let mut accounts = vec![
+        AccountMeta::new(vault_address, false),
+        AccountMeta::new(refund, false),
+    ];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: close_ix_data,
+    }
+}
+
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+// Create a fresh owner's wSOL associated token account, returning it, the owner and its rent
+fn create_wsol_account(svm: &mut LiteSVM, payer: &Keypair) -> (Pubkey, Pubkey, u64) {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM).unwrap();
+    let ata_program = Pubkey::from_str("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL").unwrap();
+    let native_mint = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+
+    let owner = Keypair::new().pubkey();
+    let (wsol, _) = Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), native_mint.as_ref()],
+        &ata_program,
+    );
+    let create = Instruction {
+        program_id: ata_program,
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(wsol, false),
+            AccountMeta::new_readonly(owner, false),
+            AccountMeta::new_readonly(native_mint, false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+        data: vec![1], // CreateIdempotent
+    };
+    send(svm, &[create], payer, "Create wSOL account").expect("create failed");
+    let rent = svm.get_account(&wsol).unwrap().lamports;
+
+    (wsol, owner, rent)
+}
+
+// Assert a wSOL account holds `amount` on top of its rent, as lamports and as its token balance
+fn assert_wsol_balance(svm: &LiteSVM, wsol: &Pubkey, owner: &Pubkey, rent: u64, amount: u64) {
+    let wsol_account = svm.get_account(wsol).unwrap();
+    assert_eq!(wsol_account.lamports, rent + amount);
+
+    // Token account layout: mint | owner | amount
+    assert_eq!(wsol_account.owner, Pubkey::from_str(TOKEN_PROGRAM).unwrap());
+    assert_eq!(wsol_account.data[32..64], owner.to_bytes());
+    assert_eq!(
+        wsol_account.data[64..72],
+        amount.to_le_bytes(),
+        "token balance doesn't match the vault's lamports"
+    );
+}
+
+// With a rent collector configured the token account only gets the vault's funds, not its rent
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_quantum_vault_close_into_wrapped_sol() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+
+    // The owner's wSOL account, created up front as it doesn't fit next to the signature
+    let (wsol, owner, wsol_rent) = create_wsol_account(&mut svm, &payer);
+
+    let sync_native = Instruction {
+        program_id: Pubkey::from_str(TOKEN_PROGRAM).unwrap(),
+        accounts: vec![AccountMeta::new(wsol, false)],
+        data: vec![17], // SyncNative
+    };
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), wsol),
+            sync_native,
+        ],
+        &payer,
+        "Close into wSOL",
+    )
+    .expect("close into wSOL failed");
+
+    assert_wsol_balance(&svm, &wsol, &owner, wsol_rent, vault_balance);
+}
+
+// Same as above, with the close syncing the token account itself
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_quantum_vault_close_syncs_wrapped_sol() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    let (wsol, owner, wsol_rent) = create_wsol_account(&mut svm, &payer);
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM).unwrap();
+
+    // Syncing needs a token account as the refund
+    let refund = Keypair::new().pubkey();
+    let mut ix = close_ix((&vault_keypair, vault_address, bump), refund);
+    ix.accounts.push(AccountMeta::new_readonly(token_program, false));
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU), ix],
+        &payer,
+        "Sync a system account",
+    );
+    assert_program_error(result, "InvalidAccountOwner");
+
+    let mut ix = close_ix((&vault_keypair, vault_address, bump), wsol);
+    ix.accounts.push(AccountMeta::new_readonly(token_program, false));
+    send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU), ix],
+        &payer,
+        "Close into wSOL with sync",
+    )
+    .expect("close into wSOL with sync failed");
+
+    assert!(svm.get_account(&vault_address).is_none());
+    assert_wsol_balance(&svm, &wsol, &owner, wsol_rent, vault_balance);
+}
+
+#[cfg(feature = "rent-collector")]
+fn rent_collector() -> Pubkey {
+    Pubkey::from(quantum_vault_pinocchio::rent_collector::RENT_COLLECTOR)
+}
+
+#[test]
+pub fn test_quantum_vault_merge_by_closing_into_new_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (keypair_a, vault_a, bump_a) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let (keypair_b, vault_b, bump_b) = setup_funded_vault(&mut svm, 3 * LAMPORTS_PER_SOL);
+    let (_, destination, _) = setup_funded_vault(&mut svm, 0);
+
+    let balance_a = svm.get_account(&vault_a).unwrap().lamports;
+    let balance_b = svm.get_account(&vault_b).unwrap().lamports;
+
+    // Two signatures don't fit in one transaction, so each source is closed on its own
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&keypair_a, vault_a, bump_a), destination),
+        ],
+        &payer,
+        "Close A into destination",
+    )
+    .expect("Failed to close vault A");
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&keypair_b, vault_b, bump_b), destination),
+        ],
+        &payer,
+        "Close B into destination",
+    )
+    .expect("Failed to close vault B");
+
+    let destination_account = svm.get_account(&destination).unwrap();
+    assert_eq!(destination_account.owner, program_id());
+    assert_balance_with_rent(&svm, &destination, balance_a + balance_b);
+
+    for source in [vault_a, vault_b] {
+        let source_after = svm.get_account(&source);
+        assert!(source_after.is_none() || source_after.unwrap().lamports == 0);
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_split_refund_includes_rent() {
+    let (mut svm, payer) = setup_svm();
+    let funded = 3 * LAMPORTS_PER_SOL + 123_456_789;
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, funded);
+
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+    let split_amount = LAMPORTS_PER_SOL + 987_654;
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(1_400_000),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                split_amount,
+            ),
+        ],
+        &payer,
+        "Split",
+    )
+    .expect("Failed to split vault");
+
+    // The rent reserve goes to the refund along with the rest of the balance
+    let split_lamports = svm.get_account(&split_account.pubkey()).unwrap().lamports;
+    assert_eq!(split_lamports, split_amount);
+    assert_balance_with_rent(&svm, &refund_account.pubkey(), funded - split_amount);
+}
+
+#[test]
+pub fn test_quantum_vault_open_rejects_existing_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+
+    let mut open_data = vault_keypair.pubkey().merklize().to_vec();
+    open_data.push(bump);
+
+    let result = send(
+        &mut svm,
+        &[open_ix(0, &payer.pubkey(), &vault_address, &open_data)],
+        &payer,
+        "Open existing vault",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyExists);
+
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+}
+
+#[test]
+pub fn test_quantum_vault_merklize_is_deterministic() {
+    let vault_keypair = WinternitzPrivkey::generate();
+    let other_keypair = WinternitzPrivkey::generate();
+
+    // Open derives the vault from one merklization, every spend from another
+    let hash = vault_keypair.pubkey().merklize();
+    assert_eq!(hash, vault_keypair.pubkey().merklize());
+    assert_ne!(hash, other_keypair.pubkey().merklize());
+
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let (rederived_address, rederived_bump) = Pubkey::find_program_address(
+        &[vault_keypair.pubkey().merklize().as_ref()],
+        &program_id(),
+    );
+    assert_eq!((vault_address, bump), (rederived_address, rederived_bump));
+
+    let (other_address, _) = Pubkey::find_program_address(
+        &[other_keypair.pubkey().merklize().as_ref()],
+        &program_id(),
+    );
+    assert_ne!(vault_address, other_address);
+}
+
+#[test]
+pub fn test_quantum_vault_signatures_are_domain_separated() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+
+    // A zero-amount split signature replayed as a close to the same refund account
+    let split_signature: [u8; 896] = vault_keypair
+        .sign(&split_message(0, &split_account.pubkey(), &refund_account.pubkey()))
+        .into();
+
+    let mut close_ix_data = vec![2u8];
+    close_ix_data.extend_from_slice(&split_signature);
+    close_ix_data.push(bump);
+
+    let close_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(refund_account.pubkey(), false),
+        ],
+        data: close_ix_data,
+    };
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU), close_ix],
+        &payer,
+        "Split signature as close",
+    );
+    let err = result.expect_err("split signature was accepted by close");
+    assert!(format!("{:?}", err.err).contains("MissingRequiredSignature"));
+
+    // A close signature replayed as a zero-amount split to the same refund account
+    let close_signature: [u8; 896] = vault_keypair
+        .sign(&close_message(&refund_account.pubkey()))
+        .into();
+
+    let mut split_ix_data = vec![1u8];
+    split_ix_data.extend_from_slice(&close_signature);
+    split_ix_data.push(bump);
+    split_ix_data.extend_from_slice(&0u64.to_le_bytes());
+
+    let split_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(split_account.pubkey(), false),
+            AccountMeta::new(refund_account.pubkey(), false),
+        ],
+        data: split_ix_data,
+    };
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(SPLIT_CU), split_ix],
+        &payer,
+        "Close signature as split",
+    );
+    let err = result.expect_err("close signature was accepted by split");
+    assert!(format!("{:?}", err.err).contains("MissingRequiredSignature"));
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+}
+
+#[test]
+pub fn test_quantum_vault_rejects_wrong_domain_tag() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+
+    // Split and close messages laid out correctly but tagged for another instruction, or for the
+    // same instruction under another ABI version
+    for tag in [2, 3, 13, 0x11] {
+        let amount = LAMPORTS_PER_SOL / 10;
+        let mut ix = split_ix((&vault_keypair, vault_address, bump), split, refund, amount);
+        let mut message = split_message(amount, &split, &refund);
+        message[0] = tag;
+        let signature: [u8; 896] = vault_keypair.sign(&message).into();
+        ix.data[1..897].copy_from_slice(&signature);
+
+        let result = send(
+            &mut svm,
+            &[compute_budget_ix(SPLIT_CU), ix],
+            &payer,
+            "Split with wrong tag",
+        );
+        assert_program_error(result, "MissingRequiredSignature");
+    }
+
+    for tag in [1, 4, 0x12] {
+        let mut ix = close_ix((&vault_keypair, vault_address, bump), refund);
+        let mut message = close_message(&refund);
+        message[0] = tag;
+        let signature: [u8; 896] = vault_keypair.sign(&message).into();
+        ix.data[1..897].copy_from_slice(&signature);
+
+        let result = send(
+            &mut svm,
+            &[compute_budget_ix(CLOSE_CU), ix],
+            &payer,
+            "Close with wrong tag",
+        );
+        assert_program_error(result, "MissingRequiredSignature");
+    }
+
+    assert_eq!(
+        svm.get_account(&vault_address).unwrap().lamports,
+        vault_balance_before
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_close_rejects_unprefixed_signature() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+    let refund = Keypair::new().pubkey();
+
+    // Signed over the bare refund pubkey, without the close discriminator in front
+    let signature: [u8; 896] = vault_keypair.sign(refund.as_ref()).into();
+
+    let mut close_ix_data = vec![2u8];
+    close_ix_data.extend_from_slice(&signature);
+    close_ix_data.push(bump);
+
+    let close_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(refund, false),
+        ],
+        data: close_ix_data,
+    };
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU), close_ix],
+        &payer,
+        "Close with unprefixed signature",
+    );
+    assert_program_error(result, "MissingRequiredSignature");
+
+    assert_eq!(
+        svm.get_account(&vault_address).unwrap().lamports,
+        vault_balance_before
+    );
+    assert!(svm.get_account(&refund).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_split_rent_only_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 0);
+
+    assert_balance_with_rent(&svm, &vault_address, 0);
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+
+    // Existing recipients, so neither needs to receive a rent-exempt amount of its own
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+    svm.airdrop(&split_account.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+    svm.airdrop(&refund_account.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+    let split_amount = 10_000;
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                split_amount,
+            ),
+        ],
+        &payer,
+        "Split rent-only vault",
+    )
+    .expect("Failed to split rent-only vault");
+
+    let split_lamports = svm.get_account(&split_account.pubkey()).unwrap().lamports;
+    let refund_lamports = svm.get_account(&refund_account.pubkey()).unwrap().lamports;
+    assert_eq!(split_lamports, LAMPORTS_PER_SOL + split_amount);
+    assert_eq!(refund_lamports, LAMPORTS_PER_SOL + rent - split_amount);
+
+    // No dust left behind
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_close_rent_only_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 0);
+
+    let refund_account = Keypair::new();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), refund_account.pubkey()),
+        ],
+        &payer,
+        "Close rent-only vault",
+    )
+    .expect("Failed to close rent-only vault");
+
+    assert_balance_with_rent(&svm, &refund_account.pubkey(), 0);
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_split_rejects_tampered_amount() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+    let signed_amount = LAMPORTS_PER_SOL;
+    let tampered_amount = 4 * LAMPORTS_PER_SOL;
+
+    // Signed over one amount, submitted with another
+    let signature: [u8; 896] = vault_keypair
+        .sign(&split_message(
+            signed_amount,
+            &split_account.pubkey(),
+            &refund_account.pubkey(),
+        ))
+        .into();
+
+    let mut split_ix_data = vec![1u8];
+    split_ix_data.extend_from_slice(&signature);
+    split_ix_data.push(bump);
+    split_ix_data.extend_from_slice(&tampered_amount.to_le_bytes());
+
+    let split_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(split_account.pubkey(), false),
+            AccountMeta::new(refund_account.pubkey(), false),
+        ],
+        data: split_ix_data,
+    };
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(SPLIT_CU), split_ix],
+        &payer,
+        "Split with tampered amount",
+    );
+    let err = result.expect_err("split with a tampered amount succeeded");
+    assert!(format!("{:?}", err.err).contains("MissingRequiredSignature"));
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+    assert!(svm.get_account(&split_account.pubkey()).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_split_rejects_amount_beyond_balance() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_balance_before = svm.get_account(&vault_address).unwrap().lamports;
+
+    // An existing split account, so an unchecked credit of u64::MAX would overflow it
+    let split_account = Keypair::new();
+    svm.airdrop(&split_account.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+    let refund_account = Keypair::new();
+
+    for amount in [vault_balance_before + 1, u64::MAX] {
+        let result = send(
+            &mut svm,
+            &[
+                compute_budget_ix(SPLIT_CU),
+                split_ix(
+                    (&vault_keypair, vault_address, bump),
+                    split_account.pubkey(),
+                    refund_account.pubkey(),
+                    amount,
+                ),
+            ],
+            &payer,
+            "Split beyond balance",
+        );
+        assert_vault_error(result, VaultError::InsufficientFunds);
+    }
+
+    let vault_account_after = svm.get_account(&vault_address).unwrap();
+    assert_eq!(vault_account_after.lamports, vault_balance_before);
+    let split_account_after = svm.get_account(&split_account.pubkey()).unwrap();
+    assert_eq!(split_account_after.lamports, LAMPORTS_PER_SOL);
+    assert!(svm.get_account(&refund_account.pubkey()).is_none());
+}
+
+// Assert a transaction failed with the given program error, by name
+fn assert_program_error(result: TransactionResult, error: &str) {
+    let err = match result {
+        Ok(_) => panic!("transaction succeeded, expected {}", error),
+        Err(failed) => failed.err,
+    };
+    assert!(
+        format!("{:?}", err).contains(error),
+        "expected {}, got {:?}",
+        error,
+        err
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_close_rejects_invalid_requests() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+    let refund = Keypair::new().pubkey();
+
+    // Signed by a key that doesn't own the vault
+    let wrong_keypair = WinternitzPrivkey::generate();
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&wrong_keypair, vault_address, bump), refund),
+        ],
+        &payer,
+        "Close with wrong key",
+    );
+    assert_program_error(result, "MissingRequiredSignature");
+
+    // Truncated signature
+    let mut truncated_ix = close_ix((&vault_keypair, vault_address, bump), refund);
+    truncated_ix.data.remove(1);
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU), truncated_ix],
+        &payer,
+        "Close with truncated signature",
+    );
+    assert_program_error(result, "InvalidInstructionData");
+
+    // Read-only vault, then read-only refund
+    for readonly in [0, 1] {
+        let mut readonly_ix = close_ix((&vault_keypair, vault_address, bump), refund);
+        readonly_ix.accounts[readonly].is_writable = false;
+        let result = send(
+            &mut svm,
+            &[compute_budget_ix(CLOSE_CU), readonly_ix],
+            &payer,
+            "Close with read-only account",
+        );
+        assert_program_error(result, "Immutable");
+    }
+
+    // Vault passed as its own refund account
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), vault_address),
+        ],
+        &payer,
+        "Close to vault",
+    );
+    assert_vault_error(result, VaultError::AccountAliasing);
+
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+    assert!(svm.get_account(&refund).is_none());
+}
+
+// Build a close-to-two instruction signed by the vault's winternitz key
+fn close_to_two_ix(
+    vault: (&WinternitzPrivkey, Pubkey, u8),
+    funds: Pubkey,
+    rent_refund: Pubkey,
+) -> Instruction {
+    let (vault_keypair, vault_address, bump) = vault;
+
+    let mut message = [0u8; 65];
+    message[0] = 3;
+    message[1..33].copy_from_slice(funds.as_ref());
+    message[33..65].copy_from_slice(rent_refund.as_ref());
+
+    let signature_bytes: [u8; 896] = vault_keypair.sign(&message).into();
+
+    let mut data = vec![3u8];
+    data.extend_from_slice(&signature_bytes);
+    data.push(bump);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new(funds, false),
+            AccountMeta::new(rent_refund, false),
+        ],
+        data,
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_close_to_two_rejects_closed_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let funds = Keypair::new().pubkey();
+    let rent_refund = Keypair::new().pubkey();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_to_two_ix((&vault_keypair, vault_address, bump), funds, rent_refund),
+        ],
+        &payer,
+        "Close to two",
+    )
+    .expect("Failed to close vault to two accounts");
+
+    let other_funds = Keypair::new().pubkey();
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_to_two_ix((&vault_keypair, vault_address, bump), other_funds, rent_refund),
+        ],
+        &payer,
+        "Close closed vault to two",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyClosed);
+}
+
+#[test]
+pub fn test_quantum_vault_close_to_two_rejects_unowned_vault() {
+    let (mut svm, payer) = setup_svm();
+
+    // A genuine vault state at the right address, but owned by the system program
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    svm.set_account(
+        vault_address,
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data: VaultState::new(hash, [bump], payer.pubkey().to_bytes())
+                .to_bytes()
+                .to_vec(),
+            owner: program::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_to_two_ix(
+                (&vault_keypair, vault_address, bump),
+                Keypair::new().pubkey(),
+                Keypair::new().pubkey(),
+            ),
+        ],
+        &payer,
+        "Close unowned vault to two",
+    );
+    assert_program_error(result, "InvalidAccountOwner");
+
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+}
+
+#[test]
+pub fn test_quantum_vault_close_rejects_missing_vault() {
+    let (mut svm, payer) = setup_svm();
+
+    // A vault address that was never opened
+    let vault_keypair = WinternitzPrivkey::generate();
+    let (vault_address, bump) = Pubkey::find_program_address(
+        &[vault_keypair.pubkey().merklize().as_ref()],
+        &program_id(),
+    );
+    let refund = Keypair::new().pubkey();
+
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), refund),
+        ],
+        &payer,
+        "Close missing vault",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyClosed);
+
+    // Closing a vault a second time
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), refund),
+        ],
+        &payer,
+        "Close",
+    )
+    .expect("Failed to close vault");
+
+    let other_refund = Keypair::new().pubkey();
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), other_refund),
+        ],
+        &payer,
+        "Close closed vault",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyClosed);
+}
+
+proptest! {
+    // Each case signs and verifies a winternitz signature, so keep the case count modest
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_quantum_vault_split_conserves_lamports(
+        funded in 0..100 * LAMPORTS_PER_SOL,
+        split_permille in 0..=1_000u64,
+    ) {
+        let (mut svm, payer) = setup_svm();
+        let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, funded);
+        let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+        let split_amount = (vault_balance as u128 * split_permille as u128 / 1_000) as u64;
+
+        // Existing recipients, so any amount (even dust) can be credited
+        let split_account = Keypair::new();
+        let refund_account = Keypair::new();
+        svm.airdrop(&split_account.pubkey(), LAMPORTS_PER_SOL)
+            .expect("failed to airdrop");
+        svm.airdrop(&refund_account.pubkey(), LAMPORTS_PER_SOL)
+            .expect("failed to airdrop");
+
+        send(
+            &mut svm,
+            &[
+                compute_budget_ix(SPLIT_CU),
+                split_ix(
+                    (&vault_keypair, vault_address, bump),
+                    split_account.pubkey(),
+                    refund_account.pubkey(),
+                    split_amount,
+                ),
+            ],
+            &payer,
+            "Split",
+        )
+        .expect("Failed to split vault");
+
+        let split_lamports = svm.get_account(&split_account.pubkey()).unwrap().lamports;
+        let refund_lamports = svm.get_account(&refund_account.pubkey()).unwrap().lamports;
+        let split_delta = split_lamports - LAMPORTS_PER_SOL;
+        let refund_delta = refund_lamports - LAMPORTS_PER_SOL;
+        prop_assert_eq!(split_delta, split_amount);
+        prop_assert_eq!(split_delta + refund_delta, vault_balance);
+    }
+}
+
+// Build a cancel unfunded instruction
+fn cancel_unfunded_ix(vault: Pubkey, opener: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(opener, true),
+        ],
+        data: vec![9u8],
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_cancel_unfunded() {
+    let (mut svm, payer) = setup_svm();
+
+    // Opened by the payer and never funded
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+    send(
+        &mut svm,
+        &[open_ix(0, &payer.pubkey(), &vault_address, &open_data)],
+        &payer,
+        "Open",
+    )
+    .expect("Failed to open vault");
+
+    // Someone other than the opener can't take the rent
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+    let result = send(
+        &mut svm,
+        &[cancel_unfunded_ix(vault_address, stranger.pubkey())],
+        &stranger,
+        "Cancel by stranger",
+    );
+    assert_program_error(result, "IllegalOwner");
+
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+    let payer_balance_before = svm.get_account(&payer.pubkey()).unwrap().lamports;
+
+    send(
+        &mut svm,
+        &[cancel_unfunded_ix(vault_address, payer.pubkey())],
+        &payer,
+        "Cancel unfunded",
+    )
+    .expect("Failed to cancel unfunded vault");
+
+    // Rent comes back, minus the transaction fee
+    let payer_balance_after = svm.get_account(&payer.pubkey()).unwrap().lamports;
+    assert_eq!(payer_balance_after, payer_balance_before + rent - 5_000);
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_cancel_unfunded_rejects_funded_vault() {
+    let (mut svm, payer) = setup_svm();
+
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+    send(
+        &mut svm,
+        &[open_ix(0, &payer.pubkey(), &vault_address, &open_data)],
+        &payer,
+        "Open",
+    )
+    .expect("Failed to open vault");
+
+    // A single deposited lamport belongs to the key holder
+    let deposit_ix = Instruction {
+        program_id: program::ID,
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(vault_address, false),
+        ],
+        data: {
+            let mut data = vec![2, 0, 0, 0]; // Transfer instruction discriminator
+            data.extend_from_slice(&1u64.to_le_bytes());
+            data
+        },
+    };
+    send(&mut svm, &[deposit_ix], &payer, "Deposit").expect("Failed to deposit");
+    let vault_account = svm.get_account(&vault_address).unwrap();
+
+    let result = send(
+        &mut svm,
+        &[cancel_unfunded_ix(vault_address, payer.pubkey())],
+        &payer,
+        "Cancel funded vault",
+    );
+    assert_vault_error(result, VaultError::VaultNotEmpty);
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account);
+}
+
+// Needs the program built with `cargo build-sbf --features verbose-logs`
+#[cfg(feature = "verbose-logs")]
+#[test]
+pub fn test_quantum_vault_logs_pda_mismatch() {
+    let (mut svm, payer) = setup_svm();
+    let (_, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    let wrong_keypair = WinternitzPrivkey::generate();
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&wrong_keypair, vault_address, bump), Keypair::new().pubkey()),
+        ],
+        &payer,
+        "Close with wrong key",
+    );
+    let failed = result.expect_err("close with the wrong key succeeded");
+
+    let recovered_hash = Pubkey::from(wrong_keypair.pubkey().merklize()).to_string();
+    let logs = failed.meta.logs.join("\n");
+    assert!(logs.contains("PDA check failed"));
+    assert!(logs.contains(&recovered_hash));
+    assert!(logs.contains(&vault_address.to_string()));
+}
+
+// Needs the program built with `cargo build-sbf --features verbose-logs`
+#[cfg(feature = "verbose-logs")]
+#[test]
+pub fn test_quantum_vault_logs_split_amount_in_sol() {
+    use quantum_vault_pinocchio::logs::{format_amount, AMOUNT_MAX_LEN};
+
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 3 * LAMPORTS_PER_SOL);
+
+    let response = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                Keypair::new().pubkey(),
+                Keypair::new().pubkey(),
+                2 * LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split 2 SOL",
+    )
+    .expect("split failed");
+
+    let logs = response.logs.join("\n");
+    assert!(logs.contains("2000000000 lamports"));
+    assert!(logs.contains("2.0 SOL"));
+
+    let mut buf = [0u8; AMOUNT_MAX_LEN];
+    for (lamports, expected) in [
+        (0, "0 lamports (0.0 SOL)"),
+        (1, "1 lamports (0.000000001 SOL)"),
+        (1_500_000_000, "1500000000 lamports (1.5 SOL)"),
+        (
+            u64::MAX,
+            "18446744073709551615 lamports (18446744073.709551615 SOL)",
+        ),
+    ] {
+        assert_eq!(format_amount(lamports, &mut buf), expected);
+    }
+}
+
+// Needs the program built with `cargo build-sbf --features anchor-events`
+#[cfg(feature = "anchor-events")]
+#[test]
+pub fn test_quantum_vault_emits_anchor_split_event() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use quantum_vault_pinocchio::events::VaultSplit;
+
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 3 * LAMPORTS_PER_SOL);
+
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    let response = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split,
+                refund,
+                2 * LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split 2 SOL",
+    )
+    .expect("split failed");
+
+    let event = response
+        .logs
+        .iter()
+        .find_map(|log| log.strip_prefix("Program data: "))
+        .expect("no event logged");
+    let event = STANDARD.decode(event).expect("event isn't base64");
+
+    // discriminator | vault | split | refund | amount
+    assert_eq!(event.len(), VaultSplit::LEN);
+    assert_eq!(event[..8], VaultSplit::DISCRIMINATOR);
+    assert_eq!(event[8..40], vault_address.to_bytes());
+    assert_eq!(event[40..72], split.to_bytes());
+    assert_eq!(event[72..104], refund.to_bytes());
+    assert_eq!(event[104..], (2 * LAMPORTS_PER_SOL).to_le_bytes());
+}
+
+// With a rent collector configured the reserve isn't spendable (test_quantum_vault_rent_collector)
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_quantum_vault_split_may_spend_rent_reserve() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+
+    // An existing refund account, as a new one must receive at least a vault's rent
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+    svm.airdrop(&refund_account.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    // The vault is closed by the split, so its rent is spendable: one lamport past the
+    // deposited funds is accepted, and only the rest of the rent is refunded
+    let split_amount = 2 * LAMPORTS_PER_SOL + 1;
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                split_amount,
+            ),
+        ],
+        &payer,
+        "Split into rent reserve",
+    )
+    .expect("Failed to split into the rent reserve");
+
+    let split_lamports = svm.get_account(&split_account.pubkey()).unwrap().lamports;
+    let refund_lamports = svm.get_account(&refund_account.pubkey()).unwrap().lamports;
+    assert_eq!(split_lamports, split_amount);
+    assert_eq!(refund_lamports, LAMPORTS_PER_SOL + vault_balance - split_amount);
+}
+
+// With a rent collector configured the reserve goes to the collector instead of the refund
+#[cfg(not(feature = "rent-collector"))]
+#[test]
+pub fn test_quantum_vault_split_all_but_one_lamport() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+
+    // Leave a single lamport of the funds above the rent reserve
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+    let split_amount = vault_balance - rent - 1;
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                split_amount,
+            ),
+        ],
+        &payer,
+        "Split all but one lamport",
+    )
+    .expect("Failed to split all but one lamport");
+
+    let split_lamports = svm.get_account(&split_account.pubkey()).unwrap().lamports;
+    let refund_lamports = svm.get_account(&refund_account.pubkey()).unwrap().lamports;
+    assert_eq!(split_lamports, split_amount);
+    assert_eq!(refund_lamports, rent + 1);
+
+    let vault_account = svm.get_account(&vault_address);
+    assert!(vault_account.is_none() || vault_account.unwrap().lamports == 0);
+}
+
+// Needs the `client` feature
+#[cfg(feature = "client")]
+#[test]
+pub fn test_quantum_vault_split_matches_client_preview() {
+    use quantum_vault_pinocchio::client::{preview_split, SplitOutcome};
+    use solana_sdk::rent::Rent;
+
+    let (mut svm, payer) = setup_svm();
+    let rent = svm.get_sysvar::<Rent>();
+
+    // (amount out of a 3 SOL deposit, split account's balance, refund account's balance)
+    for (amount, split_lamports, refund_lamports) in [
+        (LAMPORTS_PER_SOL, 0, 0),
+        (3 * LAMPORTS_PER_SOL, 0, 0),
+        (LAMPORTS_PER_SOL / 2, LAMPORTS_PER_SOL, 2 * LAMPORTS_PER_SOL),
+        (1_000, 0, LAMPORTS_PER_SOL),
+        (4 * LAMPORTS_PER_SOL, 0, 0),
+    ] {
+        let (vault_keypair, vault_address, bump) =
+            setup_funded_vault(&mut svm, 3 * LAMPORTS_PER_SOL);
+        let vault_lamports = svm.get_account(&vault_address).unwrap().lamports;
+
+        let split = Keypair::new().pubkey();
+        let refund = Keypair::new().pubkey();
+        for (account, lamports) in [(split, split_lamports), (refund, refund_lamports)] {
+            if lamports > 0 {
+                svm.airdrop(&account, lamports).expect("failed to airdrop");
+            }
+        }
+
+        let preview = preview_split(
+            vault_lamports,
+            amount,
+            split_lamports,
+            refund_lamports,
+            &rent,
+        );
+        let result = send(
+            &mut svm,
+            &[
+                compute_budget_ix(SPLIT_CU),
+                split_ix(
+                    (&vault_keypair, vault_address, bump),
+                    split,
+                    refund,
+                    amount,
+                ),
+            ],
+            &payer,
+            "Split",
+        );
+
+        let lamports = |account: &Pubkey| {
+            svm.get_account(account)
+                .map_or(0, |account| account.lamports)
+        };
+        match preview {
+            Ok(SplitOutcome {
+                split: split_expected,
+                refund: refund_expected,
+                vault: vault_expected,
+                collector: _,
+            }) => {
+                result.expect("split failed");
+                assert_eq!(lamports(&split), split_expected);
+                assert_eq!(lamports(&refund), refund_expected);
+                assert_eq!(lamports(&vault_address), vault_expected);
+            }
+            Err(error) => assert_program_error(result, &format!("{:?}", error)),
+        }
+    }
+}
+
+#[cfg(feature = "diagnose")]
+#[test]
+pub fn test_quantum_vault_recover_pubkey_compute_units() {
+    let (mut svm, payer) = setup_svm();
+    let vault_keypair = WinternitzPrivkey::generate();
+
+    // Recovery cost depends on the signed digest, so sample several messages
+    let mut consumed = Vec::new();
+    for i in 0..8u8 {
+        let message = [i; 32];
+        let signature_bytes: [u8; 896] = vault_keypair.sign(&message).into();
+
+        let mut debug_ix_data = vec![6u8]; // Discriminator
+        debug_ix_data.extend_from_slice(&signature_bytes);
+        debug_ix_data.extend_from_slice(&message);
+
+        let debug_ix = Instruction {
+            program_id: program_id(),
+            accounts: vec![],
+            data: debug_ix_data,
+        };
+
+        let response = send(
+            &mut svm,
+            &[compute_budget_ix(1_400_000), debug_ix],
+            &payer,
+            "Recover pubkey",
+        )
+        .expect("Failed to recover pubkey hash");
+        consumed.push(response.compute_units_consumed);
+    }
+
+    let min = *consumed.iter().min().unwrap();
+    let max = *consumed.iter().max().unwrap();
+    let avg = consumed.iter().sum::<u64>() / consumed.len() as u64;
+    println!("recover_pubkey + merklize compute units (default W, 8 digests)");
+    println!("| min | avg | max |");
+    println!("| {} | {} | {} |", min, avg, max);
+
+    // Baseline envelope: ~550k on average, and always within the recommended spend limits
+    assert!((300_000..800_000).contains(&avg));
+    assert!(max < SPLIT_CU as u64 && max < CLOSE_CU as u64);
+}
+
+#[test]
+pub fn test_quantum_vault_close_compute_units() {
+    let (mut svm, payer) = setup_svm();
+
+    // Each close signs a different refund account, hence a different digest
+    let mut consumed = Vec::new();
+    for _ in 0..6 {
+        let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+        let response = send(
+            &mut svm,
+            &[
+                compute_budget_ix(CLOSE_CU),
+                close_ix((&vault_keypair, vault_address, bump), Keypair::new().pubkey()),
+            ],
+            &payer,
+            "Close",
+        )
+        .expect("Failed to close vault within CLOSE_CU");
+        consumed.push(response.compute_units_consumed);
+    }
+
+    let min = *consumed.iter().min().unwrap();
+    let max = *consumed.iter().max().unwrap();
+    let avg = consumed.iter().sum::<u64>() / consumed.len() as u64;
+    println!("close compute units (6 digests)");
+    println!("| min | avg | max |");
+    println!("| {} | {} | {} |", min, avg, max);
+
+    // Recovery dominates, so close costs about what split does despite its shorter message
+    assert!((300_000..800_000).contains(&avg));
+    assert!(max < CLOSE_CU as u64);
+}
+
+#[test]
+pub fn test_quantum_vault_open_rejects_non_canonical_bump() {
+    let (mut svm, payer) = setup_svm();
+
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (_, canonical_bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+
+    // The next bump down that still yields a valid (off-curve) address
+    let (vault_address, bump) = (0..canonical_bump)
+        .rev()
+        .find_map(|bump| {
+            Pubkey::create_program_address(&[hash.as_ref(), &[bump]], &program_id())
+                .ok()
+                .map(|address| (address, bump))
+        })
+        .expect("no non-canonical bump for this hash");
+
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+
+    let result = send(
+        &mut svm,
+        &[open_ix(0, &payer.pubkey(), &vault_address, &open_data)],
+        &payer,
+        "Open with non-canonical bump",
+    );
+    assert_vault_error(result, VaultError::NonCanonicalBump);
+    assert!(svm.get_account(&vault_address).is_none());
+}
+
+// Needs the program built with
+// `QUANTUM_VAULT_RENT_COLLECTOR=<pubkey> cargo build-sbf --features rent-collector`
+#[cfg(feature = "rent-collector")]
+#[test]
+pub fn test_quantum_vault_rent_collector() {
+    let (mut svm, payer) = setup_svm();
+    let collector_balance = |svm: &LiteSVM| {
+        svm.get_account(&rent_collector())
+            .map(|account| account.lamports)
+            .unwrap_or(0)
+    };
+
+    // Close: the user gets the deposit, the collector gets the rent
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let rent = svm.get_account(&vault_address).unwrap().lamports - LAMPORTS_PER_SOL;
+    let refund = Keypair::new().pubkey();
+    let collected_before = collector_balance(&svm);
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), refund),
+        ],
+        &payer,
+        "Close with rent collector",
+    )
+    .expect("close failed");
+
+    assert_eq!(svm.get_account(&refund).unwrap().lamports, LAMPORTS_PER_SOL);
+    assert_eq!(collector_balance(&svm), collected_before + rent);
+
+    // Split: only the deposit is spendable, the rent goes to the collector
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    let collected_before = collector_balance(&svm);
+
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split,
+                refund,
+                LAMPORTS_PER_SOL + 1,
+            ),
+        ],
+        &payer,
+        "Split into the rent",
+    );
+    assert_vault_error(result, VaultError::InsufficientFunds);
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split,
+                refund,
+                LAMPORTS_PER_SOL / 2,
+            ),
+        ],
+        &payer,
+        "Split with rent collector",
+    )
+    .expect("split failed");
+
+    assert_eq!(svm.get_account(&split).unwrap().lamports, LAMPORTS_PER_SOL / 2);
+    assert_eq!(svm.get_account(&refund).unwrap().lamports, LAMPORTS_PER_SOL / 2);
+    assert_eq!(collector_balance(&svm), collected_before + rent);
+}
+
+fn authorization_address(vault: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[Authorization::SEED, vault.as_ref()], &program_id())
+}
+
+// Store a split to `recipient` signed by the vault's winternitz key, paid for by `payer`, that
+// the recipient can claim until `expiry_slot`
+fn store_authorization_ix(
+    vault: (&WinternitzPrivkey, Pubkey, u8),
+    payer: Pubkey,
+    recipient: Pubkey,
+    refund: Pubkey,
+    amount: u64,
+    expiry_slot: u64,
+) -> Instruction {
+    let (vault_keypair, vault_address, bump) = vault;
+    let (authorization, authorization_bump) = authorization_address(&vault_address);
+
+    let mut message = split_message(amount, &recipient, &refund).to_vec();
+    message[0] = 10;
+    message.extend_from_slice(&expiry_slot.to_le_bytes());
+    let signature: [u8; 896] = vault_keypair.sign(&message).into();
+
+    let mut data = vec![10u8];
+    data.extend_from_slice(&signature);
+    data.push(bump);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(recipient.as_ref());
+    data.extend_from_slice(refund.as_ref());
+    data.extend_from_slice(&expiry_slot.to_le_bytes());
+    data.push(authorization_bump);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    #[allow(unused_mut)]
+    let mut accounts = vec![
+        AccountMeta::new(payer, true),
+        AccountMeta::new(vault_address, false),
+        AccountMeta::new(authorization, false),
+        AccountMeta::new_readonly(program::ID, false),
+    ];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data,
+    }
+}
+
+fn execute_authorization_ix(
+    recipient: Pubkey,
+    vault: Pubkey,
+    refund: Pubkey,
+    payer: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(recipient, true),
+            AccountMeta::new(authorization_address(&vault).0, false),
+            AccountMeta::new(refund, false),
+            AccountMeta::new(payer, false),
+        ],
+        data: vec![11u8],
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_recipient_executes_stored_authorization() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+
+    let recipient = Keypair::new();
+    let refund = Keypair::new().pubkey();
+    let amount = LAMPORTS_PER_SOL / 2;
+
+    // The key holder stores the authorization once
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            store_authorization_ix(
+                (&vault_keypair, vault_address, bump),
+                payer.pubkey(),
+                recipient.pubkey(),
+                refund,
+                amount,
+                u64::MAX,
+            ),
+        ],
+        &payer,
+        "Store authorization",
+    )
+    .expect("store authorization failed");
+
+    assert!(svm.get_account(&vault_address).is_none());
+    let (authorization, _) = authorization_address(&vault_address);
+    let stored = svm.get_account(&authorization).unwrap();
+    let rent = stored.lamports - vault_balance;
+    assert_eq!(
+        Authorization::try_from(&stored.data[..]).unwrap(),
+        Authorization::new(
+            recipient.pubkey().to_bytes(),
+            refund.to_bytes(),
+            payer.pubkey().to_bytes(),
+            amount,
+            u64::MAX,
+        )
+    );
+
+    // Anyone else can't execute it
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[execute_authorization_ix(stranger.pubkey(), vault_address, refund, payer.pubkey())],
+        &stranger,
+        "Execute by stranger",
+    );
+    assert_vault_error(result, VaultError::InvalidAuthorization);
+
+    // The recipient triggers it later, the payer covers the fee
+    let payer_before = svm.get_account(&payer.pubkey()).unwrap().lamports;
+    let tx = Transaction::new_signed_with_payer(
+        &[execute_authorization_ix(recipient.pubkey(), vault_address, refund, payer.pubkey())],
+        Some(&payer.pubkey()),
+        &[&payer, &recipient],
+        svm.latest_blockhash(),
+    );
+    let fee = svm
+        .send_transaction(tx)
+        .expect("execute authorization failed")
+        .fee;
+
+    assert!(svm.get_account(&authorization).is_none());
+    assert_eq!(svm.get_account(&recipient.pubkey()).unwrap().lamports, amount);
+    assert_eq!(svm.get_account(&refund).unwrap().lamports, vault_balance - amount);
+    assert_eq!(
+        svm.get_account(&payer.pubkey()).unwrap().lamports,
+        payer_before + rent - fee
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_expired_authorization_returns_to_refund() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+
+    let recipient = Keypair::new();
+    let refund = Keypair::new().pubkey();
+    let expiry_slot = 1_000;
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            store_authorization_ix(
+                (&vault_keypair, vault_address, bump),
+                payer.pubkey(),
+                recipient.pubkey(),
+                refund,
+                LAMPORTS_PER_SOL / 2,
+                expiry_slot,
+            ),
+        ],
+        &payer,
+        "Store authorization",
+    )
+    .expect("store authorization failed");
+
+    // Without the recipient's signature nothing moves before the expiry slot
+    let mut execute = execute_authorization_ix(
+        recipient.pubkey(),
+        vault_address,
+        refund,
+        payer.pubkey(),
+    );
+    execute.accounts[0].is_signer = false;
+    let result = send(&mut svm, &[execute.clone()], &payer, "Execute early");
+    assert_program_error(result, "MissingRequiredSignature");
+
+    // From the expiry slot on anyone can return the funds, the recipient gets nothing
+    svm.warp_to_slot(expiry_slot);
+    svm.expire_blockhash();
+    let (authorization, _) = authorization_address(&vault_address);
+    let rent = svm.get_account(&authorization).unwrap().lamports - vault_balance;
+    let payer_before = svm.get_account(&payer.pubkey()).unwrap().lamports;
+    let fee = send(&mut svm, &[execute], &payer, "Execute expired")
+        .expect("execute expired authorization failed")
+        .fee;
+
+    assert!(svm.get_account(&authorization).is_none());
+    assert!(svm.get_account(&recipient.pubkey()).is_none());
+    assert_eq!(svm.get_account(&refund).unwrap().lamports, vault_balance);
+    assert_eq!(
+        svm.get_account(&payer.pubkey()).unwrap().lamports,
+        payer_before + rent - fee
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_split_rejects_pda_of_other_program() {
+    let (mut svm, payer) = setup_svm();
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+
+    // A vault address derived from a genuine key, but under another program's ID. The state and
+    // owner are planted so that only the PDA check stands between the signature and the funds.
+    let other_program = Keypair::new().pubkey();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &other_program);
+    svm.set_account(
+        vault_address,
+        Account {
+            lamports: 2 * LAMPORTS_PER_SOL,
+            data: VaultState::new(hash, [bump], payer.pubkey().to_bytes())
+                .to_bytes()
+                .to_vec(),
+            owner: program_id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split,
+                refund,
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split vault of another program",
+    );
+
+    // The signature is valid for the message, but the PDA check derives with this program's ID
+    assert_program_error(result, "MissingRequiredSignature");
+    assert_eq!(svm.get_account(&vault_address).unwrap().lamports, 2 * LAMPORTS_PER_SOL);
+    assert!(svm.get_account(&split).is_none());
+}
+
+// Needs the program built with `cargo build-sbf --features strict-validation`
+#[cfg(feature = "strict-validation")]
+#[test]
+pub fn test_quantum_vault_strict_validation_rejections() {
+    let (mut svm, payer) = setup_svm();
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    let split_with = |svm: &mut LiteSVM, ix: Instruction, label: &str| {
+        send(svm, &[compute_budget_ix(SPLIT_CU), ix], &payer, label)
+    };
+
+    // Owner: a vault account not owned by the program
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    svm.set_account(
+        vault_address,
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data: VaultState::new(hash, [bump], payer.pubkey().to_bytes())
+                .to_bytes()
+                .to_vec(),
+            owner: program::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    let ix = split_ix((&vault_keypair, vault_address, bump), split, refund, 1);
+    assert_program_error(
+        split_with(&mut svm, ix, "Unowned vault"),
+        "InvalidAccountOwner",
+    );
+
+    // Canonicity: a genuine vault state at a non-canonical PDA of its hash
+    let non_canonical_bump = (0..bump)
+        .rev()
+        .find(|b| Pubkey::create_program_address(&[hash.as_ref(), &[*b]], &program_id()).is_ok())
+        .expect("no non-canonical bump");
+    let non_canonical_vault =
+        Pubkey::create_program_address(&[hash.as_ref(), &[non_canonical_bump]], &program_id())
+            .unwrap();
+    svm.set_account(
+        non_canonical_vault,
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data: VaultState::new(hash, [non_canonical_bump], payer.pubkey().to_bytes())
+                .to_bytes()
+                .to_vec(),
+            owner: program_id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    let ix = split_ix(
+        (&vault_keypair, non_canonical_vault, non_canonical_bump),
+        split,
+        refund,
+        1,
+    );
+    assert_vault_error(
+        split_with(&mut svm, ix, "Non-canonical vault"),
+        VaultError::NonCanonicalBump,
+    );
+
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    // Writability: a read-only refund account
+    let mut ix = split_ix((&vault_keypair, vault_address, bump), split, refund, 1);
+    ix.accounts[2].is_writable = false;
+    assert_program_error(split_with(&mut svm, ix, "Read-only refund"), "Immutable");
+
+    // Aliasing: the vault as its own refund account
+    let ix = split_ix((&vault_keypair, vault_address, bump), split, vault_address, 1);
+    assert_vault_error(
+        split_with(&mut svm, ix, "Vault as refund"),
+        VaultError::AccountAliasing,
+    );
+
+    // Signer: an open whose payer didn't sign
+    let funder = Keypair::new();
+    svm.airdrop(&funder.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+    let mut ix = open_ix(0, &funder.pubkey(), &vault_address, &open_data);
+    ix.accounts[0].is_signer = false;
+    assert_program_error(
+        send(&mut svm, &[ix], &payer, "Unsigned open"),
+        "MissingRequiredSignature",
+    );
+
+    // Nothing was spent or opened
+    assert!(svm.get_account(&split).is_none());
+    assert!(svm.get_account(&refund).is_none());
+    assert!(svm.get_account(&vault_address).is_none());
+}
+
+// Pay `recipient` and move the change into the vault of `new_keypair`, signed by the old key
+fn rotate_ix(
+    vault: (&WinternitzPrivkey, Pubkey, u8),
+    recipient: Pubkey,
+    new_keypair: &WinternitzPrivkey,
+    amount: u64,
+) -> (Instruction, Pubkey, u8) {
+    let (vault_keypair, vault_address, bump) = vault;
+    let new_hash = new_keypair.pubkey().merklize();
+    let (new_vault, new_bump) = Pubkey::find_program_address(&[new_hash.as_ref()], &program_id());
+
+    let mut message = split_message(amount, &recipient, &Pubkey::from(new_hash));
+    message[0] = 12;
+    let signature: [u8; 896] = vault_keypair.sign(&message).into();
+
+    let mut data = vec![12u8];
+    data.extend_from_slice(&signature);
+    data.push(bump);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&new_hash);
+    data.push(new_bump);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    #[allow(unused_mut)]
+    let mut accounts = vec![
+        AccountMeta::new(vault_address, false),
+        AccountMeta::new(recipient, false),
+        AccountMeta::new(new_vault, false),
+        AccountMeta::new_readonly(program::ID, false),
+    ];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+
+    let ix = Instruction {
+        program_id: program_id(),
+        accounts,
+        data,
+    };
+
+    (ix, new_vault, new_bump)
+}
+
+#[test]
+pub fn test_quantum_vault_rotate_pays_recipient_and_keeps_change_in_new_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let vault_account = svm.get_account(&vault_address).unwrap();
+
+    let recipient = Keypair::new().pubkey();
+    let new_keypair = WinternitzPrivkey::generate();
+    let amount = LAMPORTS_PER_SOL / 2;
+    let (ix, new_vault, new_bump) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        recipient,
+        &new_keypair,
+        amount,
+    );
+
+    send(&mut svm, &[compute_budget_ix(SPLIT_CU), ix], &payer, "Rotate")
+        .expect("rotate failed");
+
+    assert!(svm.get_account(&vault_address).is_none());
+    assert_eq!(svm.get_account(&recipient).unwrap().lamports, amount);
+
+    // The change, rent included, now sits in an open vault of the new key, with no opener since
+    // the key holder paid its rent
+    let new_vault_account = svm.get_account(&new_vault).unwrap();
+    assert_eq!(new_vault_account.owner, program_id());
+    assert_eq!(new_vault_account.lamports, vault_account.lamports - amount);
+    assert_eq!(
+        VaultState::try_from(&new_vault_account.data[..]).unwrap(),
+        VaultState::new(
+            new_keypair.pubkey().merklize(),
+            [new_bump],
+            VaultState::NO_OPENER
+        )
+    );
+
+    // And the new key can spend it
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&new_keypair, new_vault, new_bump),
+                split,
+                refund,
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split from new vault",
+    )
+    .expect("split from the new vault failed");
+
+    assert!(svm.get_account(&new_vault).is_none());
+    assert_eq!(svm.get_account(&split).unwrap().lamports, LAMPORTS_PER_SOL);
+    assert_eq!(
+        svm.get_account(&refund).unwrap().lamports,
+        new_vault_account.lamports - LAMPORTS_PER_SOL
+    );
+}
+
+#[test]
+pub fn test_quantum_vault_rotated_vault_cannot_be_cancelled_by_old_opener() {
+    let (mut svm, payer) = setup_svm();
+    let opener = Keypair::new();
+    svm.airdrop(&opener.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    let vault_keypair = WinternitzPrivkey::generate();
+    let hash = vault_keypair.pubkey().merklize();
+    let (vault_address, bump) = Pubkey::find_program_address(&[hash.as_ref()], &program_id());
+    let mut open_data = hash.to_vec();
+    open_data.push(bump);
+    send(
+        &mut svm,
+        &[open_ix(0, &opener.pubkey(), &vault_address, &open_data)],
+        &opener,
+        "Open",
+    )
+    .expect("Failed to open vault");
+    svm.airdrop(&vault_address, LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    // Pay out everything but the new vault's rent, which the key holder's change covers
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+    #[cfg(feature = "rent-collector")]
+    let amount = LAMPORTS_PER_SOL - rent;
+    #[cfg(not(feature = "rent-collector"))]
+    let amount = LAMPORTS_PER_SOL;
+    let (ix, new_vault, _) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        Keypair::new().pubkey(),
+        &WinternitzPrivkey::generate(),
+        amount,
+    );
+    send(&mut svm, &[compute_budget_ix(SPLIT_CU), ix], &payer, "Rotate")
+        .expect("rotate failed");
+    let new_vault_account = svm.get_account(&new_vault).unwrap();
+    assert_eq!(new_vault_account.lamports, rent);
+
+    // The old vault's opener didn't pay that rent and can't take it
+    let result = send(
+        &mut svm,
+        &[cancel_unfunded_ix(new_vault, opener.pubkey())],
+        &opener,
+        "Cancel rotated vault",
+    );
+    assert_program_error(result, "IllegalOwner");
+    assert_eq!(svm.get_account(&new_vault).unwrap(), new_vault_account);
+}
+
+#[test]
+pub fn test_quantum_vault_top_up_recommits_to_fresh_key() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_lamports = svm.get_account(&vault_address).unwrap().lamports;
+
+    // Deposit to the new key's vault address and rotate into it in one transaction, paying
+    // nothing out, so the top-up never sits under the old key
+    let deposit = 2 * LAMPORTS_PER_SOL;
+    let new_keypair = WinternitzPrivkey::generate();
+    let (rotate, new_vault, new_bump) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        payer.pubkey(),
+        &new_keypair,
+        0,
+    );
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            solana_system_interface::instruction::transfer(&payer.pubkey(), &new_vault, deposit),
+            rotate,
+        ],
+        &payer,
+        "Top up",
+    )
+    .expect("top up failed");
+
+    assert!(svm.get_account(&vault_address).is_none());
+    let new_vault_account = svm.get_account(&new_vault).unwrap();
+    assert_eq!(new_vault_account.owner, program_id());
+    assert_eq!(new_vault_account.lamports, vault_lamports + deposit);
+
+    // Both the old balance and the deposit are spendable under the new key
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&new_keypair, new_vault, new_bump),
+                split,
+                refund,
+                deposit,
+            ),
+        ],
+        &payer,
+        "Split from topped up vault",
+    )
+    .expect("split from the topped up vault failed");
+
+    assert_eq!(svm.get_account(&split).unwrap().lamports, deposit);
+    assert_eq!(svm.get_account(&refund).unwrap().lamports, vault_lamports);
+}
+
+#[test]
+pub fn test_quantum_vault_rotate_rejects_invalid_requests() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+
+    // Dust to an empty recipient would leave it below its rent-exempt minimum
+    let (ix, _, _) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        Keypair::new().pubkey(),
+        &WinternitzPrivkey::generate(),
+        1,
+    );
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(SPLIT_CU), ix],
+        &payer,
+        "Rotate dust",
+    );
+    assert_vault_error(result, VaultError::RecipientNotRentExempt);
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+
+    let (ix, _, _) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        payer.pubkey(),
+        &WinternitzPrivkey::generate(),
+        0,
+    );
+    send(&mut svm, &[compute_budget_ix(SPLIT_CU), ix], &payer, "Rotate")
+        .expect("rotate failed");
+
+    // Rotating the same vault again, now an empty system account
+    let (ix, _, _) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        payer.pubkey(),
+        &WinternitzPrivkey::generate(),
+        0,
+    );
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(SPLIT_CU), ix],
+        &payer,
+        "Rotate closed vault",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyClosed);
+}
+
+// Needs the program built with
+// `QUANTUM_VAULT_RENT_COLLECTOR=<pubkey> cargo build-sbf --features rent-collector`
+#[cfg(feature = "rent-collector")]
+#[test]
+pub fn test_quantum_vault_rent_collector_rotate_and_store_authorization() {
+    let (mut svm, payer) = setup_svm();
+    let collector_balance = |svm: &LiteSVM| {
+        svm.get_account(&rent_collector())
+            .map(|account| account.lamports)
+            .unwrap_or(0)
+    };
+
+    // Rotate: the old vault's rent goes to the collector, the new vault's comes out of the change
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let rent = svm.get_account(&vault_address).unwrap().lamports - LAMPORTS_PER_SOL;
+    let recipient = Keypair::new().pubkey();
+    let collected_before = collector_balance(&svm);
+
+    let (ix, new_vault, _) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        recipient,
+        &WinternitzPrivkey::generate(),
+        LAMPORTS_PER_SOL / 2,
+    );
+    send(&mut svm, &[compute_budget_ix(SPLIT_CU), ix], &payer, "Rotate")
+        .expect("rotate failed");
+
+    assert_eq!(svm.get_account(&recipient).unwrap().lamports, LAMPORTS_PER_SOL / 2);
+    assert_eq!(svm.get_account(&new_vault).unwrap().lamports, LAMPORTS_PER_SOL / 2);
+    assert_eq!(collector_balance(&svm), collected_before + rent);
+
+    // Store authorization: only the deposit moves into the authorization
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let collected_before = collector_balance(&svm);
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            store_authorization_ix(
+                (&vault_keypair, vault_address, bump),
+                payer.pubkey(),
+                Keypair::new().pubkey(),
+                Keypair::new().pubkey(),
+                LAMPORTS_PER_SOL / 2,
+                u64::MAX,
+            ),
+        ],
+        &payer,
+        "Store authorization",
+    )
+    .expect("store authorization failed");
+
+    let (authorization, _) = authorization_address(&vault_address);
+    assert_eq!(
+        svm.get_account(&authorization).unwrap().lamports,
+        LAMPORTS_PER_SOL + svm.minimum_balance_for_rent_exemption(Authorization::LEN)
+    );
+    assert_eq!(collector_balance(&svm), collected_before + rent);
+}
+
+#[test]
+pub fn test_quantum_vault_low_compute_budget_fails_fast_with_advice() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+
+    // No compute budget instruction: the default 200,000 units can't cover a recovery
+    let result = send(
+        &mut svm,
+        &[close_ix((&vault_keypair, vault_address, bump), Keypair::new().pubkey())],
+        &payer,
+        "Close with default budget",
+    );
+    let failed = result.expect_err("close with the default budget succeeded");
+
+    let expected = format!("Custom({}))", VaultError::InsufficientComputeBudget as u32);
+    assert!(format!("{:?}", failed.err).ends_with(&expected));
+    let logs = failed.meta.logs.join("\n");
+    assert!(logs.contains("SetComputeUnitLimit"), "no advice in logs:\n{}", logs);
+    assert!(!logs.contains("exceeded CUs meter"));
+
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+}
+
+#[test]
+pub fn test_quantum_vault_split_without_compute_budget_fails_fast() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+
+    // Split alone, left at the default 200,000 units
+    let result = send(
+        &mut svm,
+        &[split_ix(
+            (&vault_keypair, vault_address, bump),
+            split,
+            refund,
+            LAMPORTS_PER_SOL / 2,
+        )],
+        &payer,
+        "Split with default budget",
+    );
+    let failed = result.expect_err("split with the default budget succeeded");
+
+    let expected = format!("Custom({}))", VaultError::InsufficientComputeBudget as u32);
+    assert!(format!("{:?}", failed.err).ends_with(&expected));
+    let logs = failed.meta.logs.join("\n");
+    assert!(logs.contains("SetComputeUnitLimit"), "no advice in logs:\n{}", logs);
+
+    // Nothing moved
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+    assert!(svm.get_account(&split).is_none());
+    assert!(svm.get_account(&refund).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_second_split_in_same_block_fails_cleanly() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+
+    // Two spends of the same vault, submitted back to back without advancing the blockhash
+    let first = (Keypair::new().pubkey(), Keypair::new().pubkey());
+    let second = (Keypair::new().pubkey(), Keypair::new().pubkey());
+    let blockhash = svm.latest_blockhash();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                first.0,
+                first.1,
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "First split",
+    )
+    .expect("first split failed");
+    assert_eq!(svm.latest_blockhash(), blockhash);
+
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                second.0,
+                second.1,
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Second split",
+    );
+    assert_vault_error(result, VaultError::VaultAlreadyClosed);
+
+    // Only the first spend moved anything
+    assert!(svm.get_account(&vault_address).is_none());
+    assert_eq!(svm.get_account(&first.0).unwrap().lamports, LAMPORTS_PER_SOL);
+    assert_eq!(
+        svm.get_account(&first.1).unwrap().lamports,
+        vault_balance - LAMPORTS_PER_SOL
+    );
+    assert!(svm.get_account(&second.0).is_none());
+    assert!(svm.get_account(&second.1).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_split_recipient_creation() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+    let refund = Keypair::new().pubkey();
+
+    // A new split account would be created below the rent-exempt minimum
+    let split = Keypair::new().pubkey();
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix((&vault_keypair, vault_address, bump), split, refund, 1_000),
+        ],
+        &payer,
+        "Split below rent",
+    );
+    assert_vault_error(result, VaultError::RecipientNotRentExempt);
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+
+    // A zero-lamport account last owned by another program doesn't exist as far as the runtime is
+    // concerned: the split creates it as a plain system account
+    let other_program = Keypair::new().pubkey();
+    svm.set_account(
+        split,
+        Account {
+            lamports: 0,
+            data: vec![],
+            owner: other_program,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split,
+                refund,
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "Split to zero-lamport program account",
+    )
+    .expect("split failed");
+
+    let split_account = svm.get_account(&split).unwrap();
+    assert_eq!(split_account.lamports, LAMPORTS_PER_SOL);
+    assert_eq!(split_account.owner, program::ID);
+    assert!(split_account.data.is_empty());
+}
+
+// Build a split-many instruction paying `recipients` their amounts, signed by the vault's key
+fn split_many_ix(
+    vault: (&WinternitzPrivkey, Pubkey, u8),
+    recipients: &[(Pubkey, u64)],
+    refund: Pubkey,
+) -> Instruction {
+    let (vault_keypair, vault_address, bump) = vault;
+
+    let amounts: Vec<u8> = recipients
+        .iter()
+        .flat_map(|(_, amount)| amount.to_le_bytes())
+        .collect();
+    let keys: Vec<[u8; 32]> = recipients.iter().map(|(key, _)| key.to_bytes()).collect();
+    let total = recipients.iter().map(|(_, amount)| amount).sum();
+
+    let recipients_hash = SplitMany::recipients_hash(&amounts, &keys).unwrap();
+    let message = SplitMany::message(total, &recipients_hash, &refund.to_bytes()).unwrap();
+    let signature_bytes: [u8; 896] = vault_keypair.sign(message.as_bytes()).into();
+
+    let mut data = vec![*SplitMany::DISCRIMINATOR];
+    data.extend_from_slice(&signature_bytes);
+    data.push(bump);
+    data.extend_from_slice(&amounts);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    let mut accounts = vec![
+        AccountMeta::new(vault_address, false),
+        AccountMeta::new(refund, false),
+    ];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+    accounts.extend(recipients.iter().map(|(key, _)| AccountMeta::new(*key, false)));
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data,
+    }
+}
+
+#[test]
+pub fn test_quantum_vault_split_many_pays_five_recipients() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+
+    let recipients: Vec<(Pubkey, u64)> = (1..=5u64)
+        .map(|i| (Keypair::new().pubkey(), i * LAMPORTS_PER_SOL / 10))
+        .collect();
+    let refund_account = Keypair::new();
+
+    // The commitment is the hash of every (amount, recipient) pair, in order
+    let mut preimage = Vec::new();
+    for (key, amount) in &recipients {
+        preimage.extend_from_slice(&amount.to_le_bytes());
+        preimage.extend_from_slice(key.as_ref());
+    }
+    let amounts: Vec<u8> = recipients
+        .iter()
+        .flat_map(|(_, amount)| amount.to_le_bytes())
+        .collect();
+    let keys: Vec<[u8; 32]> = recipients.iter().map(|(key, _)| key.to_bytes()).collect();
+    assert_eq!(
+        SplitMany::recipients_hash(&amounts, &keys).unwrap(),
+        solana_nostd_sha256::hashv(&[preimage.as_slice()])
+    );
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(1_400_000),
+            split_many_ix(
+                (&vault_keypair, vault_address, bump),
+                &recipients,
+                refund_account.pubkey(),
+            ),
+        ],
+        &payer,
+        "Split many",
+    )
+    .expect("Failed to split to five recipients");
+
+    for (key, amount) in &recipients {
+        assert_eq!(svm.get_account(key).unwrap().lamports, *amount);
+    }
+
+    // The refund account gets the rest, including the vault's rent unless it's collected
+    let total: u64 = recipients.iter().map(|(_, amount)| amount).sum();
+    #[cfg(not(feature = "rent-collector"))]
+    assert_balance_with_rent(&svm, &refund_account.pubkey(), 5 * LAMPORTS_PER_SOL - total);
+    #[cfg(feature = "rent-collector")]
+    assert_eq!(
+        svm.get_account(&refund_account.pubkey()).unwrap().lamports,
+        5 * LAMPORTS_PER_SOL - total
+    );
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+
+    // Paying a recipient an amount other than the signed one fails verification
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 5 * LAMPORTS_PER_SOL);
+    let mut ix = split_many_ix(
+        (&vault_keypair, vault_address, bump),
+        &recipients,
+        refund_account.pubkey(),
+    );
+    let last = ix.data.len() - 8;
+    ix.data[last..].copy_from_slice(&1u64.to_le_bytes());
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), ix],
+        &payer,
+        "Tampered split many",
+    );
+    assert_program_error(result, "MissingRequiredSignature");
+
+    // More recipients than a spend message holds are rejected before verification
+    let recipients: Vec<(Pubkey, u64)> = (0..SpendMessage::MAX_RECIPIENTS)
+        .map(|_| (Keypair::new().pubkey(), LAMPORTS_PER_SOL / 10))
+        .collect();
+    let mut ix = split_many_ix(
+        (&vault_keypair, vault_address, bump),
+        &recipients,
+        refund_account.pubkey(),
+    );
+    ix.accounts.push(AccountMeta::new(Keypair::new().pubkey(), false));
+    ix.data.extend_from_slice(&(LAMPORTS_PER_SOL / 10).to_le_bytes());
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(1_400_000), ix],
+        &payer,
+        "Split many past the recipient limit",
+    );
+    assert_vault_error(result, VaultError::MessageTooLong);
+}
+
+#[test]
+pub fn test_quantum_vault_end_to_end() {
+    let (mut svm, payer) = setup_svm();
+
+    // Open, fund and split one vault
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 2 * LAMPORTS_PER_SOL);
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split_account.pubkey(),
+                refund_account.pubkey(),
+                LAMPORTS_PER_SOL,
+            ),
+        ],
+        &payer,
+        "End-to-end split",
+    )
+    .expect("Failed to split vault");
+
+    assert_eq!(
+        svm.get_account(&split_account.pubkey()).unwrap().lamports,
+        LAMPORTS_PER_SOL
+    );
+    assert!(svm.get_account(&refund_account.pubkey()).unwrap().lamports >= LAMPORTS_PER_SOL);
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+
+    // Open, fund and close a second one
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let close_refund = Keypair::new();
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_ix((&vault_keypair, vault_address, bump), close_refund.pubkey()),
+        ],
+        &payer,
+        "End-to-end close",
+    )
+    .expect("Failed to close vault");
+
+    assert!(svm.get_account(&close_refund.pubkey()).unwrap().lamports >= LAMPORTS_PER_SOL);
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_split_bps_pays_a_quarter() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, 4 * LAMPORTS_PER_SOL);
+    let split_account = Keypair::new();
+    let refund_account = Keypair::new();
+
+    let bps = 2500u16.to_le_bytes();
+    let message = SplitBps::message(
+        &bps,
+        &split_account.pubkey().to_bytes(),
+        &refund_account.pubkey().to_bytes(),
+    );
+    let signature: [u8; 896] = vault_keypair.sign(&message).into();
+
+    let mut data = vec![*SplitBps::DISCRIMINATOR];
+    data.extend_from_slice(&signature);
+    data.push(bump);
+    data.extend_from_slice(&bps);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    #[allow(unused_mut)]
+    let mut accounts = vec![
+        AccountMeta::new(vault_address, false),
+        AccountMeta::new(split_account.pubkey(), false),
+        AccountMeta::new(refund_account.pubkey(), false),
+    ];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+
+    // The share is taken from whatever the vault holds when the split runs
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    #[cfg(feature = "rent-collector")]
+    let vault_balance = vault_balance - svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            Instruction {
+                program_id: program_id(),
+                accounts,
+                data,
+            },
+        ],
+        &payer,
+        "Split 2500 bps",
+    )
+    .expect("Failed to split by basis points");
+
+    let quarter = vault_balance / 4;
+    assert_eq!(SplitBps::share(vault_balance, 2500), quarter);
+    assert_eq!(
+        svm.get_account(&split_account.pubkey()).unwrap().lamports,
+        quarter
+    );
+    assert_eq!(
+        svm.get_account(&refund_account.pubkey()).unwrap().lamports,
+        vault_balance - quarter
+    );
+
+    // Shares round down and never exceed the balance
+    assert_eq!(SplitBps::share(999, 2500), 249);
+    assert_eq!(SplitBps::share(u64::MAX, 10_000), u64::MAX);
+    assert_eq!(SplitBps::share(u64::MAX, 0), 0);
+}
+
+#[test]
+pub fn test_quantum_vault_split_rejects_under_rented_rollover() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+    let rent = svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+
+    #[cfg(feature = "rent-collector")]
+    let spendable = vault_account_before.lamports - rent;
+    #[cfg(not(feature = "rent-collector"))]
+    let spendable = vault_account_before.lamports;
+
+    // Roll over into the (unopened) vault of a fresh key, leaving it one lamport short of rent
+    let new_hash = WinternitzPrivkey::generate().pubkey().merklize();
+    let (new_vault, _) = Pubkey::find_program_address(&[new_hash.as_ref()], &program_id());
+    let split = Keypair::new().pubkey();
+
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(SPLIT_CU),
+            split_ix(
+                (&vault_keypair, vault_address, bump),
+                split,
+                new_vault,
+                spendable - (rent - 1),
+            ),
+        ],
+        &payer,
+        "Under-rented rollover",
+    );
+    assert_vault_error(result, VaultError::RecipientNotRentExempt);
+
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+    assert!(svm.get_account(&new_vault).is_none());
+}
+
+#[test]
+pub fn test_quantum_vault_close_proportional_50_30_20() {
+    let (mut svm, payer) = setup_svm();
+    // An odd balance, so the shares don't divide it evenly
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL + 7);
+
+    let refunds: Vec<(Pubkey, u16)> = [5_000u16, 3_000, 2_000]
+        .into_iter()
+        .map(|bps| (Keypair::new().pubkey(), bps))
+        .collect();
+    let shares: Vec<u8> = refunds
+        .iter()
+        .flat_map(|(_, bps)| bps.to_le_bytes())
+        .collect();
+    let keys: Vec<[u8; 32]> = refunds.iter().map(|(key, _)| key.to_bytes()).collect();
+
+    let shares_hash = CloseProportional::shares_hash(&shares, &keys).unwrap();
+    let message = CloseProportional::message(&shares_hash);
+    let signature: [u8; 896] = vault_keypair.sign(&message).into();
+
+    let mut data = vec![*CloseProportional::DISCRIMINATOR];
+    data.extend_from_slice(&signature);
+    data.push(bump);
+    data.extend_from_slice(&shares);
+    // No passphrase
+    #[cfg(feature = "passphrase-salt")]
+    data.extend_from_slice(&[0; 32]);
+
+    let mut accounts = vec![AccountMeta::new(vault_address, false)];
+    #[cfg(feature = "rent-collector")]
+    accounts.push(AccountMeta::new(rent_collector(), false));
+    accounts.extend(refunds.iter().map(|(key, _)| AccountMeta::new(*key, false)));
+
+    let vault_balance = svm.get_account(&vault_address).unwrap().lamports;
+    #[cfg(feature = "rent-collector")]
+    let vault_balance = vault_balance - svm.minimum_balance_for_rent_exemption(VaultState::LEN);
+
+    send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            Instruction {
+                program_id: program_id(),
+                accounts,
+                data,
+            },
+        ],
+        &payer,
+        "Close proportionally",
+    )
+    .expect("Failed to close proportionally");
+
+    // The first two shares round down, the last account takes the remainder
+    let half = vault_balance * 5_000 / 10_000;
+    let thirty = vault_balance * 3_000 / 10_000;
+    let lamports = |key: &Pubkey| svm.get_account(key).unwrap().lamports;
+    assert_eq!(lamports(&refunds[0].0), half);
+    assert_eq!(lamports(&refunds[1].0), thirty);
+    assert_eq!(lamports(&refunds[2].0), vault_balance - half - thirty);
+
+    let vault_account_after = svm.get_account(&vault_address);
+    assert!(vault_account_after.is_none() || vault_account_after.unwrap().lamports == 0);
+}
+
+#[test]
+pub fn test_quantum_vault_split_rejects_forged_bump() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+
+    // The signature is valid for the message; only the bump is changed. The bump isn't signed,
+    // but it's hashed into the PDA check, so the vault no longer derives from the recovered key.
+    let mut ix = split_ix(
+        (&vault_keypair, vault_address, bump),
+        Keypair::new().pubkey(),
+        Keypair::new().pubkey(),
+        LAMPORTS_PER_SOL / 2,
+    );
+    assert_eq!(ix.data[897], bump);
+    ix.data[897] = bump.wrapping_sub(1);
+
+    let result = send(
+        &mut svm,
+        &[compute_budget_ix(SPLIT_CU), ix],
+        &payer,
+        "Split with forged bump",
+    );
+    #[cfg(not(feature = "strict-validation"))]
+    assert_program_error(result, "MissingRequiredSignature");
+    #[cfg(feature = "strict-validation")]
+    assert_vault_error(result, VaultError::NonCanonicalBump);
+
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+}
+
+#[test]
+pub fn test_quantum_vault_signer_seeds_sign_for_vault() {
+    let (mut svm, payer) = setup_svm();
+    let (vault_keypair, vault_address, bump) = setup_funded_vault(&mut svm, LAMPORTS_PER_SOL);
+
+    // The seeds derive the vault address
+    let hash = vault_keypair.pubkey().merklize();
+    let bump_seed = [bump];
+    let seeds = vault_signer_seeds(&hash, &bump_seed);
+    assert_eq!(
+        Pubkey::create_program_address(&seeds, &program_id()).unwrap(),
+        vault_address
+    );
+
+    // Rotating into a PDA that already holds lamports signs Allocate and Assign CPIs for it with
+    // these seeds; they fail unless the seeds derive the account
+    let recipient = Keypair::new().pubkey();
+    let new_keypair = WinternitzPrivkey::generate();
+    let (ix, new_vault, _) = rotate_ix(
+        (&vault_keypair, vault_address, bump),
+        recipient,
+        &new_keypair,
+        LAMPORTS_PER_SOL / 2,
+    );
+    svm.airdrop(&new_vault, LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+    let vault_lamports = svm.get_account(&vault_address).unwrap().lamports;
+
+    send(&mut svm, &[compute_budget_ix(SPLIT_CU), ix], &payer, "Rotate")
+        .expect("rotate into a prefunded vault address failed");
+
+    let new_vault_account = svm.get_account(&new_vault).unwrap();
+    assert_eq!(new_vault_account.owner, program_id());
+    assert_eq!(
+        new_vault_account.lamports,
+        LAMPORTS_PER_SOL + vault_lamports - LAMPORTS_PER_SOL / 2
+    );
+}
+
+// Open and fund a vault under the seed of a fresh winternitz key and `salt`
+#[cfg(feature = "passphrase-salt")]
+fn setup_salted_vault(
+    svm: &mut LiteSVM,
+    payer: &Keypair,
+    salt: &[u8; 32],
+) -> (WinternitzPrivkey, Pubkey, u8) {
+    use quantum_vault_pinocchio::vault::vault_seed;
+
+    let vault_keypair = WinternitzPrivkey::generate();
+    let seed = vault_seed(&vault_keypair.pubkey().merklize(), salt);
+    let (vault_address, bump) = Pubkey::find_program_address(&[seed.as_ref()], &program_id());
+
+    let mut open_data = seed.to_vec();
+    open_data.push(bump);
+    send(
+        svm,
+        &[open_ix(0, &payer.pubkey(), &vault_address, &open_data)],
+        payer,
+        "Open salted vault",
+    )
+    .expect("open failed");
+    svm.airdrop(&vault_address, LAMPORTS_PER_SOL)
+        .expect("failed to airdrop");
+
+    (vault_keypair, vault_address, bump)
+}
+
+// Replace the all-zero salt a test helper appends to a spend's instruction data
+#[cfg(feature = "passphrase-salt")]
+fn with_salt(mut ix: Instruction, salt: &[u8; 32]) -> Instruction {
+    let salt_offset = ix.data.len() - 32;
+    ix.data[salt_offset..].copy_from_slice(salt);
+    ix
+}
+
+// Needs the program built with `cargo build-sbf --features passphrase-salt`
+#[cfg(feature = "passphrase-salt")]
+#[test]
+pub fn test_quantum_vault_passphrase_salted_vault() {
+    let (mut svm, payer) = setup_svm();
+
+    // Open the vault under the seed of the winternitz key and the passphrase
+    let salt = solana_nostd_sha256::hashv(&[b"correct horse battery staple".as_slice()]);
+    let (vault_keypair, vault_address, bump) = setup_salted_vault(&mut svm, &payer, &salt);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+
+    // The same key without the passphrase (or with another one) doesn't derive the vault
+    let split = Keypair::new().pubkey();
+    let refund = Keypair::new().pubkey();
+    let wrong_salt = solana_nostd_sha256::hashv(&[b"wrong passphrase".as_slice()]);
+    for salt in [[0; 32], wrong_salt] {
+        let ix = split_ix(
+            (&vault_keypair, vault_address, bump),
+            split,
+            refund,
+            LAMPORTS_PER_SOL / 2,
+        );
+
+        let result = send(
+            &mut svm,
+            &[compute_budget_ix(SPLIT_CU), with_salt(ix, &salt)],
+            &payer,
+            "Split with wrong passphrase",
+        );
+        assert_program_error(result, "MissingRequiredSignature");
+    }
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+
+    // With the passphrase it spends
+    let ix = split_ix(
+        (&vault_keypair, vault_address, bump),
+        split,
+        refund,
+        LAMPORTS_PER_SOL / 2,
+    );
+
+    send(
+        &mut svm,
+        &[compute_budget_ix(SPLIT_CU), with_salt(ix, &salt)],
+        &payer,
+        "Split with passphrase",
+    )
+    .expect("split with the passphrase failed");
+    assert_eq!(svm.get_account(&split).unwrap().lamports, LAMPORTS_PER_SOL / 2);
+}
+
+// Needs the program built with `cargo build-sbf --features passphrase-salt`
+#[cfg(feature = "passphrase-salt")]
+#[test]
+pub fn test_quantum_vault_passphrase_salted_close_to_two() {
+    let (mut svm, payer) = setup_svm();
+    let salt = solana_nostd_sha256::hashv(&[b"correct horse battery staple".as_slice()]);
+    let (vault_keypair, vault_address, bump) = setup_salted_vault(&mut svm, &payer, &salt);
+    let vault_account_before = svm.get_account(&vault_address).unwrap();
+    let funds = Keypair::new().pubkey();
+    let rent_refund = Keypair::new().pubkey();
+
+    // Without the passphrase the key alone doesn't derive the vault
+    let result = send(
+        &mut svm,
+        &[
+            compute_budget_ix(CLOSE_CU),
+            close_to_two_ix((&vault_keypair, vault_address, bump), funds, rent_refund),
+        ],
+        &payer,
+        "Close to two without passphrase",
+    );
+    assert_program_error(result, "MissingRequiredSignature");
+    assert_eq!(svm.get_account(&vault_address).unwrap(), vault_account_before);
+
+    let ix = close_to_two_ix((&vault_keypair, vault_address, bump), funds, rent_refund);
+    send(
+        &mut svm,
+        &[compute_budget_ix(CLOSE_CU + 1), with_salt(ix, &salt)],
+        &payer,
+        "Close to two with passphrase",
+    )
+    .expect("close to two with the passphrase failed");
+
+    assert!(svm.get_account(&vault_address).is_none());
+    assert_eq!(svm.get_account(&funds).unwrap().lamports, LAMPORTS_PER_SOL);
+    assert_balance_with_rent(&svm, &rent_refund, 0);
+}